@@ -0,0 +1,122 @@
+use crate::{
+  catalog::file_io,
+  common::result::{ErrorType, Location, Result},
+  err,
+};
+use apache_avro::Reader;
+use serde_json::Value;
+use std::{fs::File, io::BufReader, io::ErrorKind};
+
+// Reader of the Avro files of Iceberg tables, i.e. their manifest lists and manifests. Records are
+// decoded with the schema of the file into JSON values, bytes and fixed values become arrays of
+// numbers.
+
+fn invalid<T>(location: &str, e: impl std::fmt::Display) -> Result<T> {
+  err!(
+    ErrorType::BadRequest,
+    Location::Table,
+    format!("Invalid Avro file {}: {}", location, e)
+  )
+}
+
+// Read all the records of the Avro file at `location`.
+pub fn read_records(location: &str) -> Result<Vec<Value>> {
+  let path = file_io::local_path(location)?;
+  let file = match File::open(&path) {
+    Ok(file) => file,
+    Err(e) if e.kind() == ErrorKind::NotFound => {
+      return err!(
+        ErrorType::NotFound,
+        Location::Table,
+        format!("Avro file {} not found", location)
+      )
+    }
+    Err(e) => {
+      return err!(
+        ErrorType::InternalError,
+        Location::Table,
+        format!("Failed to read Avro file {}: {}", location, e)
+      )
+    }
+  };
+  let reader = match Reader::new(BufReader::new(file)) {
+    Ok(reader) => reader,
+    Err(e) => return invalid(location, e),
+  };
+  let mut records = vec![];
+  for record in reader {
+    match record.and_then(Value::try_from) {
+      Ok(record) => records.push(record),
+      Err(e) => return invalid(location, e),
+    }
+  }
+  Ok(records)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+  use super::*;
+  use apache_avro::{types, Codec, DeflateSettings, Schema, Writer, ZstandardSettings};
+  use serde_json::json;
+
+  // Write an Avro file whose records only have string fields, like the paths of manifest lists
+  // and manifests. The fields can be nested in records, e.g. `data_file.file_path`.
+  pub fn write_records(path: &std::path::Path, records: &[Value], codec: Codec) {
+    fn schema_of(value: &Value, name: &str) -> Value {
+      match value {
+        Value::Object(fields) => json!({
+          "type": "record",
+          "name": name,
+          "fields": fields
+            .iter()
+            .map(|(field, value)| json!({ "name": field, "type": schema_of(value, field) }))
+            .collect::<Vec<_>>(),
+        }),
+        _ => json!(["null", "string"]),
+      }
+    }
+
+    let schema = Schema::parse(&schema_of(&records[0], "entry")).unwrap();
+    let mut writer = Writer::with_codec(&schema, vec![], codec);
+    for record in records {
+      let record = types::Value::from(record.clone()).resolve(&schema).unwrap();
+      writer.append(record).unwrap();
+    }
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, writer.into_inner().unwrap()).unwrap();
+  }
+
+  #[test]
+  fn test_read_records() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let records = vec![
+      json!({ "status": "a", "data_file": { "file_path": "/x/1.parquet", "format": null } }),
+      json!({ "status": "b", "data_file": { "file_path": "/x/2.parquet", "format": "PARQUET" } }),
+    ];
+    let codecs = [
+      Codec::Null,
+      Codec::Deflate(DeflateSettings::default()),
+      Codec::Snappy,
+      Codec::Zstandard(ZstandardSettings::default()),
+    ];
+    for (i, codec) in codecs.into_iter().enumerate() {
+      let path = temp_dir.path().join(format!("{}.avro", i));
+      write_records(&path, &records, codec);
+      let read = read_records(path.to_str().unwrap()).unwrap();
+      assert_eq!(read, records);
+    }
+
+    let path = temp_dir.path().join("truncated.avro");
+    write_records(&path, &records, Codec::Null);
+    let data = std::fs::read(&path).unwrap();
+    std::fs::write(&path, &data[..data.len() - 20]).unwrap();
+    let error = read_records(path.to_str().unwrap()).err().unwrap();
+    assert!(matches!(error.error_type, ErrorType::BadRequest));
+
+    let error = read_records(temp_dir.path().join("missing.avro").to_str().unwrap());
+    assert!(matches!(
+      error.err().unwrap().error_type,
+      ErrorType::NotFound
+    ));
+  }
+}
@@ -0,0 +1,234 @@
+use crate::{
+  catalog::{
+    metric::Metric,
+    namespace::{Namespace, NamespaceIdent},
+    table::Table,
+  },
+  common::result::{ErrorType, Location, Result},
+  db::{key, DBConnection, DBRead, Key, Transaction},
+  err,
+  util::time,
+};
+use rocket::serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{sync::Weak, thread, time::Duration};
+
+// dropped tables and namespaces are kept this long when the config does not say otherwise
+pub const DEFAULT_RETENTION_MS: i64 = 7 * 24 * 60 * 60 * 1000;
+
+// how often the sweeper looks for dropped tables and namespaces whose retention expired
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum DroppedType {
+  Table,
+  Namespace,
+}
+
+// A dropped table or namespace, as listed to admins.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct Dropped {
+  pub id: String,
+  #[serde(rename = "type")]
+  pub type_: DroppedType,
+  // the namespace of a table, or the namespace itself
+  pub namespace: Vec<NamespaceIdent>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub name: Option<String>,
+  pub dropped_ms: i64,
+  pub expires_ms: i64,
+}
+
+// What is needed to restore the dropped table or namespace.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+struct StoredDropped {
+  #[serde(flatten)]
+  dropped: Dropped,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  table: Option<Table>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  properties: Option<Value>,
+}
+
+impl Dropped {
+  fn retain(txn: &mut Transaction, stored: StoredDropped) -> Result<()> {
+    txn.put(&Key::Dropped(stored.dropped.id.clone()), &stored)
+  }
+
+  fn new(type_: DroppedType, namespace: &[NamespaceIdent], retention_ms: i64) -> Dropped {
+    let dropped_ms = time::now_millis();
+    Dropped {
+      id: uuid::Uuid::new_v4().to_string(),
+      type_,
+      namespace: namespace.to_vec(),
+      name: None,
+      dropped_ms,
+      expires_ms: dropped_ms.saturating_add(retention_ms),
+    }
+  }
+
+  // Keep a table in the transaction that drops it.
+  pub fn retain_table(
+    txn: &mut Transaction,
+    namespace: &[NamespaceIdent],
+    table: Table,
+    retention_ms: i64,
+  ) -> Result<Dropped> {
+    let dropped = Dropped {
+      name: Some(table.name.clone()),
+      ..Dropped::new(DroppedType::Table, namespace, retention_ms)
+    };
+    let stored = StoredDropped {
+      dropped: dropped.clone(),
+      table: Some(table),
+      properties: None,
+    };
+    Dropped::retain(txn, stored)?;
+    Ok(dropped)
+  }
+
+  // Keep an empty namespace in the transaction that drops it.
+  pub fn retain_namespace(
+    txn: &mut Transaction,
+    level: &[NamespaceIdent],
+    properties: Value,
+    retention_ms: i64,
+  ) -> Result<()> {
+    let stored = StoredDropped {
+      dropped: Dropped::new(DroppedType::Namespace, level, retention_ms),
+      table: None,
+      properties: Some(properties),
+    };
+    Dropped::retain(txn, stored)
+  }
+
+  // All the dropped tables and namespaces that can still be restored, oldest drop first.
+  pub fn list(conn: &DBConnection) -> Result<Vec<Dropped>> {
+    let mut dropped = vec![];
+    for key in conn.scan(&Key::prefix(key::DROPPED, &[])) {
+      // restored or swept since the keys were listed
+      if let Some(stored) = conn.get::<StoredDropped>(&key)? {
+        dropped.push(stored.dropped);
+      }
+    }
+    dropped.sort_by(|a, b| (a.dropped_ms, &a.id).cmp(&(b.dropped_ms, &b.id)));
+    Ok(dropped)
+  }
+
+  // Add a dropped table or namespace back where it was dropped from. Fails if its namespace, or
+  // the parent of the namespace, is gone or if its name was taken since.
+  pub fn restore(conn: &DBConnection, id: &str) -> Result<Dropped> {
+    conn.transact(|txn| {
+      let key = Key::Dropped(id.to_string());
+      let stored: StoredDropped = match txn.get(&key)? {
+        Some(stored) => stored,
+        None => {
+          return err!(
+            ErrorType::NotFound,
+            Location::Request,
+            format!("Dropped table or namespace {} not found", key)
+          )
+        }
+      };
+      let namespace = &stored.dropped.namespace;
+      match stored.table {
+        Some(table) => Table::restore_in(txn, namespace, table)?,
+        None => {
+          let properties = stored.properties.unwrap_or_else(|| json!({}));
+          Namespace::insert_in(txn, namespace, properties)?;
+        }
+      }
+      txn.delete(&key);
+      Ok(stored.dropped)
+    })
+  }
+
+  // Forget the dropped tables and namespaces whose retention expired, along with the metrics
+  // reports of the tables. Their files are left in place, only a drop that requests a purge
+  // deletes them. Returns how many were swept.
+  pub fn sweep(conn: &DBConnection, metrics_conn: &DBConnection) -> Result<usize> {
+    let now = time::now_millis();
+    let mut swept = 0;
+    for key in conn.scan(&Key::prefix(key::DROPPED, &[])) {
+      // restored or swept since the keys were listed
+      let dropped = conn.transact(|txn| match txn.get::<StoredDropped>(&key)? {
+        Some(stored) if stored.dropped.expires_ms <= now => {
+          txn.delete(&key);
+          Ok(Some(stored.dropped))
+        }
+        _ => Ok(None),
+      })?;
+      if let Some(dropped) = dropped {
+        swept += 1;
+        Metric::delete_dropped(metrics_conn, &dropped.id)?;
+      }
+    }
+    Ok(swept)
+  }
+
+  // Sweep the catalog every `SWEEP_INTERVAL` until it is closed.
+  pub fn start_sweeper(conn: Weak<DBConnection>, metrics_conn: Weak<DBConnection>) {
+    thread::spawn(move || loop {
+      thread::sleep(SWEEP_INTERVAL);
+      let (conn, metrics_conn) = match (conn.upgrade(), metrics_conn.upgrade()) {
+        (Some(conn), Some(metrics_conn)) => (conn, metrics_conn),
+        _ => return,
+      };
+      if let Err(e) = Dropped::sweep(&conn, &metrics_conn) {
+        eprintln!("sweep of dropped tables failed: {}", e.message);
+      }
+    });
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::catalog::{
+    file_io::{self, Warehouse},
+    purge::Purge,
+  };
+  use crate::db::{Backend, DB};
+  use crate::server::routes::common::TableMetadataGenerator;
+  use crate::server::routes::table::test::mock_create_table_request;
+
+  #[test]
+  fn test_sweep_expired() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let db = DB::new(temp_dir.path().join("db"), Backend::default(), "test").unwrap();
+    let warehouse = Warehouse::new(temp_dir.path().join("warehouse")).unwrap();
+    let generator = TableMetadataGenerator::new();
+    let (conn, metrics_conn) = (db.conn(), db.metrics_conn());
+    let (a, b) = (vec!["a".to_string()], vec!["b".to_string()]);
+    Namespace::create(conn, &a, None).unwrap();
+    Namespace::create(conn, &b, None).unwrap();
+    let request = mock_create_table_request("t");
+    let table = Table::create(conn, &a, &request, (&generator).into(), &warehouse).unwrap();
+    let location = table.metadata.location.clone().unwrap();
+    let location = file_io::local_path(&location).unwrap();
+    assert!(location.exists());
+
+    // the table expires at once, the namespace is kept
+    let dropped = Table::delete(conn, &a, "t".to_string(), false, 0, &warehouse).unwrap();
+    let reports = Key::Dropped(dropped.unwrap().id);
+    metrics_conn.put(&reports, &json!([])).unwrap();
+    Namespace::delete(conn, &b, DEFAULT_RETENTION_MS).unwrap();
+    assert_eq!(Dropped::list(conn).unwrap().len(), 2);
+    assert_eq!(Dropped::sweep(conn, metrics_conn).unwrap(), 1);
+    let dropped = Dropped::list(conn).unwrap();
+    assert_eq!(dropped.len(), 1);
+    assert_eq!(dropped[0].type_, DroppedType::Namespace);
+    assert_eq!(Dropped::sweep(conn, metrics_conn).unwrap(), 0);
+
+    // the reports of the swept table are deleted, its files are not
+    assert!(!metrics_conn.exists(&reports).unwrap());
+    assert!(Purge::get(conn, &a, "t").unwrap().is_none());
+    assert!(location.exists());
+  }
+}
@@ -0,0 +1,210 @@
+use crate::{
+  common::result::{ErrorType, Location, Result},
+  err,
+  server::routes::common::TableMetadata,
+};
+use rocket::serde::Serialize;
+use std::{
+  fs,
+  io::{ErrorKind, Write},
+  path::{Component, Path, PathBuf},
+};
+
+// Root directory under which tables without an explicit location are stored.
+pub struct Warehouse {
+  root: PathBuf,
+}
+
+impl Warehouse {
+  pub fn new(root: PathBuf) -> Result<Warehouse> {
+    if let Err(e) = fs::create_dir_all(&root) {
+      return err!(
+        ErrorType::InternalError,
+        Location::Table,
+        format!("Failed to create warehouse directory {:?}: {}", root, e)
+      );
+    }
+    // table locations are stored as absolute file:// URIs
+    match fs::canonicalize(&root) {
+      Ok(root) => Ok(Warehouse { root }),
+      Err(e) => err!(
+        ErrorType::InternalError,
+        Location::Table,
+        format!("Failed to resolve warehouse directory {:?}: {}", root, e)
+      ),
+    }
+  }
+
+  pub fn root(&self) -> &Path {
+    &self.root
+  }
+
+  // The catalog only writes files under the root. Locations on other file systems are accepted,
+  // nothing is written there.
+  pub fn check_location(&self, location: &str) -> Result<()> {
+    let path = match local_path(location) {
+      Ok(path) => path,
+      Err(_) => return Ok(()),
+    };
+    // the path may not exist yet, `..` is rejected rather than resolved
+    if path.starts_with(&self.root) && !path.components().any(|x| x == Component::ParentDir) {
+      return Ok(());
+    }
+    err!(
+      ErrorType::BadRequest,
+      Location::Table,
+      format!(
+        "Location {} is outside of the warehouse {}",
+        location,
+        self.root.display()
+      )
+    )
+  }
+
+  // `<warehouse>/<level 1>/.../<level n>/<table>`, the same layout as the Iceberg JDBC catalog.
+  pub fn default_location(&self, namespace: &[String], table_name: &str) -> String {
+    let mut path = self.root.clone();
+    path.extend(namespace);
+    path.push(table_name);
+    format!("file://{}", path.display())
+  }
+}
+
+// Parse the version out of a `<version>-<uuid>.metadata.json` file name. Files written by other
+// tools may not follow this pattern.
+fn parse_version(metadata_location: &str) -> Option<i32> {
+  let file_name = metadata_location.rsplit('/').next()?;
+  file_name.split_once('-')?.0.parse().ok()
+}
+
+// Location of the next metadata file of a table, None if `metadata_dir` is not on the local file
+// system.
+pub fn new_metadata_location(metadata_dir: &str, previous: Option<&str>) -> Option<String> {
+  local_path(metadata_dir).ok()?;
+  let version = previous.and_then(parse_version).map_or(0, |v| v + 1);
+  Some(format!(
+    "{}/{:05}-{}.metadata.json",
+    metadata_dir.trim_end_matches('/'),
+    version,
+    uuid::Uuid::new_v4()
+  ))
+}
+
+// Metadata files of tables and views are never overwritten, every commit gets a new file.
+pub fn write_metadata(location: &str, metadata: &impl Serialize) -> Result<()> {
+  let path = local_path(location)?;
+  let content = match serde_json::to_vec_pretty(metadata) {
+    Ok(content) => content,
+    Err(e) => {
+      return err!(
+        ErrorType::InternalError,
+        Location::Table,
+        format!("Failed to serialize metadata {}: {}", location, e)
+      )
+    }
+  };
+  let result = path
+    .parent()
+    .map_or(Ok(()), fs::create_dir_all)
+    .and_then(|_| {
+      fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+    })
+    .and_then(|mut file| file.write_all(&content).and_then(|_| file.sync_all()));
+  match result {
+    Ok(_) => Ok(()),
+    Err(e) => err!(
+      ErrorType::InternalError,
+      Location::Table,
+      format!("Failed to write metadata file {}: {}", location, e)
+    ),
+  }
+}
+
+// Metadata files written by the attempts of a transaction. An attempt that is retried or fails
+// leaves files nothing points to, they are deleted once the transaction is over.
+#[derive(Default)]
+pub struct MetadataFiles {
+  current: Vec<String>,
+  aborted: Vec<String>,
+}
+
+impl MetadataFiles {
+  // Called at the start of every attempt, the files of the previous attempt are not used.
+  pub fn new_attempt(&mut self) {
+    self.aborted.append(&mut self.current);
+  }
+
+  pub fn write(&mut self, location: &str, metadata: &impl Serialize) -> Result<()> {
+    write_metadata(location, metadata)?;
+    self.current.push(location.to_string());
+    Ok(())
+  }
+
+  // Delete the files of the aborted attempts, and those of the last attempt if it failed.
+  pub fn finish<T>(mut self, result: Result<T>) -> Result<T> {
+    if result.is_err() {
+      self.new_attempt();
+    }
+    for location in self.aborted {
+      if let Ok(path) = local_path(&location) {
+        let _ = fs::remove_file(path);
+      }
+    }
+    result
+  }
+}
+
+// Only the local file system is supported for now, either as a plain path or a file:// URI.
+pub fn local_path(location: &str) -> Result<PathBuf> {
+  let path = match location.split_once("://") {
+    None => location,
+    Some(("file", path)) => path,
+    Some((scheme, _)) => {
+      return err!(
+        ErrorType::BadRequest,
+        Location::Table,
+        format!("Unsupported file system scheme {} in {}", scheme, location)
+      )
+    }
+  };
+  if path.is_empty() {
+    return err!(
+      ErrorType::BadRequest,
+      Location::Table,
+      "Metadata location cannot be empty".to_string()
+    );
+  }
+  Ok(PathBuf::from(path))
+}
+
+pub fn read_metadata(location: &str) -> Result<TableMetadata> {
+  let path = local_path(location)?;
+  let content = match fs::read_to_string(&path) {
+    Ok(content) => content,
+    Err(e) if e.kind() == ErrorKind::NotFound => {
+      return err!(
+        ErrorType::NotFound,
+        Location::Table,
+        format!("Metadata file {} not found", location)
+      )
+    }
+    Err(e) => {
+      return err!(
+        ErrorType::InternalError,
+        Location::Table,
+        format!("Failed to read metadata file {}: {}", location, e)
+      )
+    }
+  };
+  match serde_json::from_str(&content) {
+    Ok(metadata) => Ok(metadata),
+    Err(e) => err!(
+      ErrorType::BadRequest,
+      Location::Table,
+      format!("Invalid metadata file {}: {}", location, e)
+    ),
+  }
+}
@@ -0,0 +1,229 @@
+use crate::{
+  catalog::dropped::Dropped,
+  catalog::namespace::NamespaceIdent,
+  catalog::table::Table,
+  common::result::{ErrorType, Location, Result},
+  db::{key, DBConnection, DBRead, Key},
+  err,
+  server::routes::{common::TableMetadata, request::*},
+  util::time,
+};
+use rocket::serde::{Deserialize, Serialize};
+
+// only the most recent reports of each table are kept
+pub const MAX_REPORTS_PER_TABLE: usize = 100;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct StoredReport {
+  pub received_ms: i64,
+  #[serde(flatten)]
+  pub report: ReportMetricsRequest,
+}
+
+pub struct Metric {}
+
+impl Metric {
+  // Validate a report against the table it was sent for and store it. Reports live in their own
+  // db (`metrics_conn`) under the same key as the table.
+  pub fn report(
+    conn: &impl DBRead,
+    metrics_conn: &DBConnection,
+    namespace: &[NamespaceIdent],
+    table_name: String,
+    report: ReportMetricsRequest,
+  ) -> Result<()> {
+    let table_key = Key::table(namespace, &table_name);
+    let table = match Table::get(conn, namespace, table_name.clone())? {
+      Some(table) => table,
+      None => {
+        return err!(
+          ErrorType::NotFound,
+          Location::Table,
+          format!("Table {} not found", table_key)
+        )
+      }
+    };
+    validate_report(&report, &table_name, &table.metadata)?;
+
+    let report = StoredReport {
+      received_ms: time::now_millis(),
+      report,
+    };
+    metrics_conn.transact(|txn| {
+      let mut reports = txn
+        .get::<Vec<StoredReport>>(&table_key)?
+        .unwrap_or_default();
+      reports.push(report.clone());
+      if reports.len() > MAX_REPORTS_PER_TABLE {
+        reports.drain(..reports.len() - MAX_REPORTS_PER_TABLE);
+      }
+      txn.put(&table_key, &reports)
+    })
+  }
+
+  // The most recent reports of a table, newest first.
+  pub fn list(
+    conn: &impl DBRead,
+    metrics_conn: &impl DBRead,
+    namespace: &[NamespaceIdent],
+    table_name: String,
+    limit: usize,
+  ) -> Result<Vec<StoredReport>> {
+    let table_key = Key::table(namespace, &table_name);
+    if !conn.exists(&table_key)? {
+      return err!(
+        ErrorType::NotFound,
+        Location::Table,
+        format!("Table {} not found", table_key)
+      );
+    }
+    let reports = metrics_conn
+      .get::<Vec<StoredReport>>(&table_key)?
+      .unwrap_or_default();
+    Ok(reports.into_iter().rev().take(limit).collect())
+  }
+
+  // Move the reports of a renamed table, a new table created under the old name should not see
+  // them.
+  pub fn rename(
+    metrics_conn: &DBConnection,
+    source_namespace: &[NamespaceIdent],
+    source_name: &str,
+    destination_namespace: &[NamespaceIdent],
+    destination_name: &str,
+  ) -> Result<()> {
+    let source_key = Key::table(source_namespace, source_name);
+    let destination_key = Key::table(destination_namespace, destination_name);
+    Metric::move_reports(metrics_conn, &source_key, &destination_key)
+  }
+
+  // Move the reports of the tables of a renamed namespace and of its descendants.
+  pub fn rename_namespace(
+    metrics_conn: &DBConnection,
+    source: &[NamespaceIdent],
+    destination: &[NamespaceIdent],
+  ) -> Result<()> {
+    for key in metrics_conn.scan(&Key::prefix(key::TABLE, source)) {
+      if let Key::Table(level, name) = &key {
+        if let Some(rest) = level.strip_prefix(source) {
+          let mut moved = destination.to_vec();
+          moved.extend_from_slice(rest);
+          Metric::move_reports(metrics_conn, &key, &Key::table(&moved, name))?;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  fn move_reports(metrics_conn: &DBConnection, source: &Key, destination: &Key) -> Result<()> {
+    if source == destination {
+      return Ok(());
+    }
+    metrics_conn.transact(|txn| match txn.get::<Vec<StoredReport>>(source)? {
+      Some(reports) => {
+        txn.delete(source);
+        txn.put(destination, &reports)
+      }
+      None => Ok(()),
+    })
+  }
+
+  // Keep the reports of a dropped table with its drop, so they come back if the table is restored.
+  pub fn retain_dropped(
+    metrics_conn: &DBConnection,
+    namespace: &[NamespaceIdent],
+    table_name: &str,
+    dropped_id: &str,
+  ) -> Result<()> {
+    let table_key = Key::table(namespace, table_name);
+    let dropped_key = Key::Dropped(dropped_id.to_string());
+    Metric::move_reports(metrics_conn, &table_key, &dropped_key)
+  }
+
+  pub fn restore_dropped(metrics_conn: &DBConnection, dropped: &Dropped) -> Result<()> {
+    match &dropped.name {
+      Some(table_name) => {
+        let dropped_key = Key::Dropped(dropped.id.clone());
+        let table_key = Key::table(&dropped.namespace, table_name);
+        Metric::move_reports(metrics_conn, &dropped_key, &table_key)
+      }
+      None => Ok(()),
+    }
+  }
+
+  pub fn delete_dropped(metrics_conn: &DBConnection, dropped_id: &str) -> Result<()> {
+    metrics_conn.delete(&Key::Dropped(dropped_id.to_string()))
+  }
+
+  pub fn delete(
+    metrics_conn: &DBConnection,
+    namespace: &[NamespaceIdent],
+    table_name: String,
+  ) -> Result<()> {
+    let table_key = Key::table(namespace, &table_name);
+    metrics_conn.delete(&table_key)
+  }
+}
+
+fn invalid_report<T>(message: String) -> Result<T> {
+  err!(
+    ErrorType::BadRequest,
+    Location::Request,
+    format!("Invalid metrics report: {}", message)
+  )
+}
+
+// Clients report the full table name (`<catalog>.<namespace>.<table>`), only the last part is
+// checked since the catalog name is chosen by the client.
+fn check_table_name(reported: &str, table_name: &str) -> Result<()> {
+  if reported != table_name && !reported.ends_with(&format!(".{}", table_name)) {
+    return invalid_report(format!(
+      "report is for table {}, not {}",
+      reported, table_name
+    ));
+  }
+  Ok(())
+}
+
+fn check_snapshot(snapshot_id: i64, metadata: &TableMetadata) -> Result<()> {
+  if !metadata
+    .snapshots
+    .iter()
+    .any(|s| s.snapshot_id == snapshot_id)
+  {
+    return invalid_report(format!("unknown snapshot {}", snapshot_id));
+  }
+  Ok(())
+}
+
+fn validate_report(
+  report: &ReportMetricsRequest,
+  table_name: &str,
+  metadata: &TableMetadata,
+) -> Result<()> {
+  match report {
+    ReportMetricsRequest::Scan(scan) => {
+      check_table_name(&scan.table_name, table_name)?;
+      check_snapshot(scan.snapshot_id, metadata)?;
+      if !metadata
+        .schemas
+        .iter()
+        .any(|s| s.schema_id == scan.schema_id)
+      {
+        return invalid_report(format!("unknown schema {}", scan.schema_id));
+      }
+      if scan.projected_field_ids.len() != scan.projected_field_names.len() {
+        return invalid_report(
+          "projected field ids and names do not have the same length".to_string(),
+        );
+      }
+    }
+    ReportMetricsRequest::Commit(commit) => {
+      check_table_name(&commit.table_name, table_name)?;
+      check_snapshot(commit.snapshot_id, metadata)?;
+    }
+  }
+  Ok(())
+}
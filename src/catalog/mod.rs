@@ -1,2 +1,10 @@
+pub mod avro;
+pub mod dropped;
+pub mod file_io;
+pub mod metric;
 pub mod namespace;
+pub mod purge;
+pub mod schema;
 pub mod table;
+pub mod update;
+pub mod view;
@@ -1,38 +1,37 @@
 use crate::{
+  catalog::dropped::Dropped,
   common::result::{ErrorType, Location, Result},
   err,
-  util::time,
+  server::routes::request::PageRequest,
+  util::{
+    page::{paginate, Page},
+    time,
+  },
 };
-use rocket::serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
+use rocket::serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Map, Value};
 
-use crate::db::DBConnection;
+use crate::db::{DBConnection, DBRead, Key, Transaction};
 
 pub type NamespaceIdent = String;
 
-// we store the namespace as a string, the value should contains all the parent namespaces
+// we store the namespace under a key with all its levels (see `Key::Namespace`),
 // e.g. all the direct child to namespace A.B will be stored in the child field,
-// and the ident field will be A::B
+// and the key will be namespace/A/B
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct Namespace {
   pub child: Vec<NamespaceIdent>,
   pub properties: Value,
   pub tables: Vec<String>,
-}
-
-fn hash<'a>(level: &Vec<NamespaceIdent>) -> String {
-  if level.is_empty() {
-    "root".to_string()
-  } else {
-    format!("root::{}", level.join("::"))
-  }
+  #[serde(default)]
+  pub views: Vec<String>,
 }
 
 impl Namespace {
-  pub fn init(conn: &mut DBConnection) -> Result<()> {
-    let key = "root";
-    match conn.exists(key) {
+  pub fn init(conn: &DBConnection) -> Result<()> {
+    let key = Key::namespace(&[]);
+    match conn.exists(&key)? {
       true => Ok(()),
       false => {
         let properties = json!({
@@ -40,36 +39,43 @@ impl Namespace {
         });
         let namespace = Namespace {
           child: vec![],
-          properties: properties,
+          properties,
           tables: vec![],
+          views: vec![],
         };
-        conn.put(key, &namespace)?;
+        conn.put(&key, &namespace)?;
         Ok(())
       }
     }
   }
 
-  // exist will not return an error
-  pub fn exists(conn: &DBConnection, level: &Vec<NamespaceIdent>) -> bool {
-    let key = hash(level);
+  pub fn exists(conn: &impl DBRead, level: &[NamespaceIdent]) -> Result<bool> {
+    let key = Key::namespace(level);
     conn.exists(&key)
   }
 
-  // List all the child namespaces of the given parent namespace.
+  // List the child namespaces of the given parent namespace, a page at a time (see `paginate`).
   pub fn list(
-    conn: &DBConnection,
-    parent: &Vec<NamespaceIdent>,
-  ) -> Option<Vec<Vec<NamespaceIdent>>> {
-    let key = hash(&parent);
-    let res: Option<Namespace> = conn.get(&key);
-    if res.is_none() {
-      return None;
-    }
-    let val = res.unwrap();
-    let parent: Vec<_> = parent.into_iter().map(|x| x.to_string()).collect();
-    Some(
-      val
-        .child
+    conn: &impl DBRead,
+    parent: &[NamespaceIdent],
+    page: &PageRequest,
+  ) -> Result<Page<Vec<NamespaceIdent>>> {
+    let key = Key::namespace(parent);
+    let val: Namespace = match conn.get(&key)? {
+      Some(val) => val,
+      None => {
+        return err!(
+          ErrorType::NotFound,
+          Location::Namespace,
+          format!("Namespace {} not found", key)
+        )
+      }
+    };
+    let page = paginate(val.child, page)?;
+    let parent: Vec<_> = parent.iter().map(|x| x.to_string()).collect();
+    Ok(Page {
+      items: page
+        .items
         .into_iter()
         .map(|x| {
           let mut r = parent.clone();
@@ -77,42 +83,198 @@ impl Namespace {
           r
         })
         .collect(),
-    )
+      next_page_token: page.next_page_token,
+    })
   }
 
   pub fn create(
-    conn: &mut DBConnection,
-    level: &Vec<NamespaceIdent>,
+    conn: &DBConnection,
+    level: &[NamespaceIdent],
     properties: Option<Value>,
   ) -> Result<Namespace> {
-    let key = hash(level);
-    if Namespace::exists(conn, level) {
+    conn.transact(|txn| Namespace::create_in(txn, level, properties.clone()))
+  }
+
+  fn create_in(
+    txn: &mut Transaction,
+    level: &[NamespaceIdent],
+    properties: Option<Value>,
+  ) -> Result<Namespace> {
+    let mut properties = match properties {
+      None => Map::new(),
+      Some(Value::Object(properties)) => properties,
+      Some(_) => {
+        return err!(
+          ErrorType::BadRequest,
+          Location::Namespace,
+          "Properties must be an object".to_string()
+        )
+      }
+    };
+    properties.insert(
+      "created_at".to_string(),
+      Value::from(time::now().to_string()),
+    );
+    Namespace::insert_in(txn, level, Value::Object(properties))
+  }
+
+  // Add an empty namespace with `properties` under its parent, as created or as restored after a
+  // drop (see `Dropped::restore`).
+  pub fn insert_in(
+    txn: &mut Transaction,
+    level: &[NamespaceIdent],
+    properties: Value,
+  ) -> Result<Namespace> {
+    let key = Key::namespace(level);
+    if Namespace::exists(txn, level)? {
       return err!(
         ErrorType::AlreadyExists,
         Location::Namespace,
         format!("Namespace {} already exists", key)
       );
     }
+    let (name, parent_level) = match level.split_last() {
+      Some(split) => split,
+      None => {
+        return err!(
+          ErrorType::BadRequest,
+          Location::Namespace,
+          "Namespace cannot be empty".to_string()
+        )
+      }
+    };
+    if name.is_empty() {
+      return err!(
+        ErrorType::BadRequest,
+        Location::Namespace,
+        format!("Invalid namespace {}", key)
+      );
+    }
+    let parent_key = Key::namespace(parent_level);
+    let mut parent: Namespace = match txn.get(&parent_key)? {
+      Some(parent) => parent,
+      None => {
+        return err!(
+          ErrorType::NotFound,
+          Location::Namespace,
+          format!("Parent namespace {} not found", parent_key)
+        )
+      }
+    };
 
-    let mut old_properties = properties.unwrap_or_else(|| json!({}));
-    let new_properties = old_properties.as_object_mut().unwrap();
-    new_properties.insert(
-      "created_at".to_string(),
-      Value::from(time::now().to_string()),
-    );
     let namespace = Namespace {
       child: vec![],
-      properties: Value::Object(new_properties.to_owned()),
+      properties,
       tables: vec![],
+      views: vec![],
     };
-    conn.put(key.as_str(), &namespace)?;
+    // the namespace and the link from its parent are written together
+    parent.child.push(name.clone());
+    txn.put(&key, &namespace)?;
+    txn.put(&parent_key, &parent)?;
     Ok(namespace)
   }
 
+  // Tables and views of a namespace share one set of names, fail when `name` is taken by either.
+  pub fn check_name_free(conn: &impl DBRead, level: &[NamespaceIdent], name: &str) -> Result<()> {
+    let table_key = Key::table(level, name);
+    if conn.exists(&table_key)? {
+      return err!(
+        ErrorType::AlreadyExists,
+        Location::Table,
+        format!("Table {} already exists", table_key)
+      );
+    }
+    let view_key = Key::view(level, name);
+    if conn.exists(&view_key)? {
+      return err!(
+        ErrorType::AlreadyExists,
+        Location::View,
+        format!("View {} already exists", view_key)
+      );
+    }
+    Ok(())
+  }
+
+  // The names of the tables or of the views (`location`) of the namespace.
+  fn names(&mut self, location: &Location) -> &mut Vec<String> {
+    match location {
+      Location::View => &mut self.views,
+      _ => &mut self.tables,
+    }
+  }
+
+  // Move the table or view (`location`) `source_name` of `source_namespace` to its new key and
+  // from the list of its old namespace to the one of its new namespace. `rename` sets the new
+  // name on the stored entry.
+  pub fn move_entry<T: Serialize + DeserializeOwned>(
+    txn: &mut Transaction,
+    location: Location,
+    source_namespace: &[NamespaceIdent],
+    source_name: &str,
+    destination_namespace: &[NamespaceIdent],
+    destination_name: &str,
+    rename: impl FnOnce(&mut T),
+  ) -> Result<()> {
+    let source_namespace_key = Key::namespace(source_namespace);
+    let destination_namespace_key = Key::namespace(destination_namespace);
+    for namespace_key in [&source_namespace_key, &destination_namespace_key] {
+      if !txn.exists(namespace_key)? {
+        return err!(
+          ErrorType::NotFound,
+          Location::Namespace,
+          format!("Namespace {} not found", namespace_key)
+        );
+      }
+    }
+    let (key, kind): (fn(&[NamespaceIdent], &str) -> Key, _) = match location {
+      Location::View => (Key::view, "View"),
+      _ => (Key::table, "Table"),
+    };
+    let source_key = key(source_namespace, source_name);
+    let destination_key = key(destination_namespace, destination_name);
+    let mut entry = match txn.get::<T>(&source_key)? {
+      Some(entry) => entry,
+      None => {
+        return err!(
+          ErrorType::NotFound,
+          location,
+          format!("{} {} not found", kind, source_key)
+        )
+      }
+    };
+    if source_key == destination_key {
+      return Ok(());
+    }
+    Namespace::check_name_free(txn, destination_namespace, destination_name)?;
+
+    rename(&mut entry);
+    txn.put(&destination_key, &entry)?;
+    txn.delete(&source_key);
+
+    // within a namespace the entry keeps its place in the list, the second read of the same
+    // namespace sees the first write
+    let mut source: Namespace = txn.get(&source_namespace_key)?.unwrap();
+    let source_names = source.names(&location);
+    match source_names.iter().position(|name| name == source_name) {
+      Some(index) if source_namespace_key == destination_namespace_key => {
+        source_names[index] = destination_name.to_string();
+        return txn.put(&source_namespace_key, &source);
+      }
+      _ => source_names.retain(|name| name != source_name),
+    }
+    txn.put(&source_namespace_key, &source)?;
+    let mut destination: Namespace = txn.get(&destination_namespace_key)?.unwrap();
+    destination
+      .names(&location)
+      .push(destination_name.to_string());
+    txn.put(&destination_namespace_key, &destination)
+  }
+
   // get will return an error if the namespace does not exist
-  pub fn get_properties(conn: &DBConnection, level: &Vec<NamespaceIdent>) -> Result<Option<Value>> {
-    let key = hash(level);
-    let namespace: Option<Namespace> = conn.get(key.as_str());
+  pub fn get_properties(conn: &impl DBRead, level: &[NamespaceIdent]) -> Result<Option<Value>> {
+    let key = Key::namespace(level);
+    let namespace: Option<Namespace> = conn.get(&key)?;
     if namespace.is_none() {
       return err!(
         ErrorType::NotFound,
@@ -123,10 +285,15 @@ impl Namespace {
     Ok(Some(namespace.unwrap().properties))
   }
 
-  // get will return an error if the namespace does not exist
-  pub fn delete(conn: &mut DBConnection, level: &Vec<NamespaceIdent>) -> Result<()> {
-    let key = hash(level);
-    let namespace: Option<Namespace> = conn.get(&key);
+  // get will return an error if the namespace does not exist. The dropped namespace is kept with
+  // its properties for `retention_ms` so it can be restored.
+  pub fn delete(conn: &DBConnection, level: &[NamespaceIdent], retention_ms: i64) -> Result<()> {
+    conn.transact(|txn| Namespace::delete_in(txn, level, retention_ms))
+  }
+
+  fn delete_in(txn: &mut Transaction, level: &[NamespaceIdent], retention_ms: i64) -> Result<()> {
+    let key = Key::namespace(level);
+    let namespace: Option<Namespace> = txn.get(&key)?;
     if namespace.is_none() {
       return err!(
         ErrorType::NotFound,
@@ -142,17 +309,166 @@ impl Namespace {
         format!("Namespace {} has children", key)
       );
     }
-    conn.delete(&key)
+    if !namespace.tables.is_empty() || !namespace.views.is_empty() {
+      return err!(
+        ErrorType::NamespaceNotEmpty,
+        Location::Namespace,
+        format!("Namespace {} is not empty", key)
+      );
+    }
+    let (name, parent_level) = match level.split_last() {
+      Some(split) => split,
+      None => {
+        return err!(
+          ErrorType::BadRequest,
+          Location::Namespace,
+          "The root namespace cannot be deleted".to_string()
+        )
+      }
+    };
+
+    // unlink the namespace from its parent in the same transaction
+    let parent_key = Key::namespace(parent_level);
+    if let Some(mut parent) = txn.get::<Namespace>(&parent_key)? {
+      parent.child.retain(|x| x != name);
+      txn.put(&parent_key, &parent)?;
+    }
+    txn.delete(&key);
+    Dropped::retain_namespace(txn, level, namespace.properties, retention_ms)
+  }
+
+  // Rename a namespace with everything under it: its tables, views and all its descendant
+  // namespaces (with their tables and views) move to the new levels, and the namespace moves from
+  // the child list of its old parent to the one of its new parent, all in one transaction.
+  pub fn rename(
+    conn: &DBConnection,
+    source: &[NamespaceIdent],
+    destination: &[NamespaceIdent],
+  ) -> Result<()> {
+    conn.transact(|txn| Namespace::rename_in(txn, source, destination))
+  }
+
+  fn rename_in(
+    txn: &mut Transaction,
+    source: &[NamespaceIdent],
+    destination: &[NamespaceIdent],
+  ) -> Result<()> {
+    let source_key = Key::namespace(source);
+    let destination_key = Key::namespace(destination);
+    let (source_name, source_parent) = match source.split_last() {
+      Some(split) => split,
+      None => {
+        return err!(
+          ErrorType::BadRequest,
+          Location::Namespace,
+          "The root namespace cannot be renamed".to_string()
+        )
+      }
+    };
+    let (destination_name, destination_parent) = match destination.split_last() {
+      Some(split) if !split.0.is_empty() => split,
+      _ => {
+        return err!(
+          ErrorType::BadRequest,
+          Location::Namespace,
+          format!("Invalid namespace {}", destination_key)
+        )
+      }
+    };
+    if !Namespace::exists(txn, source)? {
+      return err!(
+        ErrorType::NotFound,
+        Location::Namespace,
+        format!("Namespace {} not found", source_key)
+      );
+    }
+    if destination.starts_with(source) {
+      return err!(
+        ErrorType::BadRequest,
+        Location::Namespace,
+        format!("Namespace {} cannot move under itself", source_key)
+      );
+    }
+    if Namespace::exists(txn, destination)? {
+      return err!(
+        ErrorType::AlreadyExists,
+        Location::Namespace,
+        format!("Namespace {} already exists", destination_key)
+      );
+    }
+    let destination_parent_key = Key::namespace(destination_parent);
+    if !Namespace::exists(txn, destination_parent)? {
+      return err!(
+        ErrorType::NotFound,
+        Location::Namespace,
+        format!("Parent namespace {} not found", destination_parent_key)
+      );
+    }
+
+    // every key of a namespace and of its tables and views embeds all its levels, so the whole
+    // subtree moves
+    let mut levels = vec![source.to_vec()];
+    while let Some(level) = levels.pop() {
+      let key = Key::namespace(&level);
+      let namespace: Namespace = match txn.get(&key)? {
+        Some(namespace) => namespace,
+        None => continue,
+      };
+      let mut new_level = destination.to_vec();
+      new_level.extend_from_slice(&level[source.len()..]);
+      let tables = namespace
+        .tables
+        .iter()
+        .map(|x| (Key::table(&level, x), Key::table(&new_level, x)));
+      let views = namespace
+        .views
+        .iter()
+        .map(|x| (Key::view(&level, x), Key::view(&new_level, x)));
+      for (old_key, new_key) in tables.chain(views) {
+        if let Some(value) = txn.get::<Value>(&old_key)? {
+          txn.put(&new_key, &value)?;
+          txn.delete(&old_key);
+        }
+      }
+      for child in namespace.child.iter() {
+        let mut child_level = level.clone();
+        child_level.push(child.clone());
+        levels.push(child_level);
+      }
+      txn.put(&Key::namespace(&new_level), &namespace)?;
+      txn.delete(&key);
+    }
+
+    // the parents may be the same namespace, the second read sees the first write
+    let source_parent_key = Key::namespace(source_parent);
+    if let Some(mut parent) = txn.get::<Namespace>(&source_parent_key)? {
+      parent.child.retain(|x| x != source_name);
+      txn.put(&source_parent_key, &parent)?;
+    }
+    if let Some(mut parent) = txn.get::<Namespace>(&destination_parent_key)? {
+      parent.child.push(destination_name.clone());
+      txn.put(&destination_parent_key, &parent)?;
+    }
+    Ok(())
   }
 
   pub fn update(
-    conn: &mut DBConnection,
-    level: &Vec<NamespaceIdent>,
+    conn: &DBConnection,
+    level: &[NamespaceIdent],
+    removals: Option<Vec<String>>,
+    updates: Option<Value>,
+  ) -> Result<Value> {
+    conn.transact(|txn| Namespace::update_in(txn, level, removals.clone(), updates.clone()))
+  }
+
+  fn update_in(
+    txn: &mut Transaction,
+    level: &[NamespaceIdent],
     removals: Option<Vec<String>>,
     updates: Option<Value>,
   ) -> Result<Value> {
-    let key = hash(level);
-    let namespace: Option<Namespace> = conn.get(&key);
+    let key = Key::namespace(level);
+    let namespace: Option<Namespace> = txn.get(&key)?;
     if namespace.is_none() {
       return err!(
         ErrorType::NotFound,
@@ -160,14 +476,34 @@ impl Namespace {
         format!("Namespace {} not found", key)
       );
     }
+    let updates = match updates {
+      None => None,
+      Some(Value::Object(updates)) => Some(updates),
+      Some(_) => {
+        return err!(
+          ErrorType::BadRequest,
+          Location::Namespace,
+          "Property updates must be an object".to_string()
+        )
+      }
+    };
     let mut namespace = namespace.unwrap();
-    let properties = namespace.properties.as_object_mut().unwrap();
+    let properties = match namespace.properties.as_object_mut() {
+      Some(properties) => properties,
+      None => {
+        return err!(
+          ErrorType::BadRequest,
+          Location::Namespace,
+          format!("Properties of namespace {} are not an object", key)
+        )
+      }
+    };
 
     let mut removed_keys = vec![];
     let mut missing_keys = vec![];
     if let Some(removals) = removals {
       for key in removals {
-        if let Some(_) = properties.remove(&key) {
+        if properties.remove(&key).is_some() {
           removed_keys.push(key);
         } else {
           missing_keys.push(key);
@@ -177,11 +513,13 @@ impl Namespace {
 
     let mut updated_keys: Vec<String> = vec![];
     if let Some(updates) = updates {
-      for (key, value) in updates.as_object().unwrap() {
-        properties.insert(key.to_string(), value.to_owned());
-        updated_keys.push(key.to_string());
+      for (key, value) in updates {
+        updated_keys.push(key.clone());
+        properties.insert(key, value);
       }
     }
+    // the changes are only reported once they are stored
+    txn.put(&key, &namespace)?;
 
     Ok(json!({
       "removed_keys": removed_keys,
@@ -0,0 +1,298 @@
+use crate::{
+  catalog::{
+    avro,
+    file_io::{self, Warehouse},
+    namespace::NamespaceIdent,
+    table::Table,
+  },
+  common::result::{ErrorType, Result},
+  db::{key, DBConnection, DBRead, Key, Transaction},
+  util::time,
+};
+use rocket::serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+  collections::HashSet,
+  fs,
+  io::{self, ErrorKind},
+  path::{Path, PathBuf},
+  sync::Arc,
+  thread,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "lowercase")]
+pub enum PurgeStatus {
+  Pending,
+  Running,
+  Done,
+  Failed,
+}
+
+// Progress of the deletion of the files of a dropped table, as reported to clients.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct Purge {
+  pub table_uuid: String,
+  pub status: PurgeStatus,
+  pub requested_ms: i64,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub finished_ms: Option<i64>,
+  #[serde(default)]
+  pub deleted_files: usize,
+  // files that could not be deleted, they are left in place
+  #[serde(default)]
+  pub failed_files: Vec<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub error: Option<String>,
+}
+
+// The dropped table is kept with its purge until it finishes, so a purge interrupted by a restart
+// can start over. Only the files under the location of the table are deleted, and only if that
+// location is in the warehouse the table was dropped from.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+struct StoredPurge {
+  #[serde(flatten)]
+  purge: Purge,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  table: Option<Table>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  warehouse_root: Option<PathBuf>,
+}
+
+impl Purge {
+  // Record a pending purge of `table` in the transaction that drops it. A purge requested earlier
+  // for a table with the same name is replaced.
+  pub fn request(
+    txn: &mut Transaction,
+    namespace: &[NamespaceIdent],
+    table: Table,
+    warehouse: &Warehouse,
+  ) -> Result<()> {
+    let key = Key::purge(namespace, &table.name);
+    let stored = StoredPurge {
+      purge: Purge {
+        table_uuid: table.metadata.table_uuid.clone(),
+        status: PurgeStatus::Pending,
+        requested_ms: time::now_millis(),
+        finished_ms: None,
+        deleted_files: 0,
+        failed_files: vec![],
+        error: None,
+      },
+      table: Some(table),
+      warehouse_root: Some(warehouse.root().to_path_buf()),
+    };
+    txn.put(&key, &stored)
+  }
+
+  // The last purge requested for a table name.
+  pub fn get(
+    conn: &impl DBRead,
+    namespace: &[NamespaceIdent],
+    table_name: &str,
+  ) -> Result<Option<Purge>> {
+    Ok(
+      conn
+        .get::<StoredPurge>(&Key::purge(namespace, table_name))?
+        .map(|stored| stored.purge),
+    )
+  }
+
+  // Delete the files of the pending purge of a table in the background.
+  pub fn start(conn: Arc<DBConnection>, namespace: &[NamespaceIdent], table_name: &str) {
+    let key = Key::purge(namespace, table_name);
+    thread::spawn(move || {
+      if let Err(e) = Purge::run(&conn, &key) {
+        eprintln!("purge of {} failed: {}", key, e.message);
+      }
+    });
+  }
+
+  // Start the purges that did not finish before the catalog stopped.
+  pub fn resume(conn: &Arc<DBConnection>) -> Result<()> {
+    for key in conn.scan(&Key::prefix(key::PURGE, &[])) {
+      if let Key::Purge(namespace, table_name) = &key {
+        if conn
+          .get::<StoredPurge>(&key)?
+          .is_some_and(|stored| stored.table.is_some())
+        {
+          Purge::start(conn.clone(), namespace, table_name);
+        }
+      }
+    }
+    Ok(())
+  }
+
+  fn run(conn: &DBConnection, key: &Key) -> Result<()> {
+    let (table, warehouse_root) = match conn.get::<StoredPurge>(key)? {
+      Some(StoredPurge {
+        table: Some(table),
+        warehouse_root,
+        ..
+      }) => (table, warehouse_root),
+      _ => return Ok(()),
+    };
+    let uuid = table.metadata.table_uuid.clone();
+    Purge::update(conn, key, &uuid, |stored| {
+      stored.purge.status = PurgeStatus::Running
+    })?;
+
+    // the metadata is written by clients, its paths can point anywhere
+    let location = table
+      .metadata
+      .location
+      .as_deref()
+      .and_then(|x| file_io::local_path(x).ok())
+      .and_then(|x| fs::canonicalize(x).ok())
+      .filter(|x| {
+        warehouse_root
+          .as_ref()
+          .is_some_and(|root| x.starts_with(root) && x != root)
+      });
+    let (mut deleted_files, mut failed_files, mut error) = (0, vec![], None);
+    match table_files(&table) {
+      Ok(files) => {
+        for file in files {
+          match remove_file(&file, location.as_deref()) {
+            Ok(_) => deleted_files += 1,
+            // already deleted by an interrupted run, or never written
+            Err(e) if e.kind() == ErrorKind::NotFound => (),
+            Err(_) => failed_files.push(file),
+          }
+        }
+        if let Some(location) = &location {
+          remove_empty_dirs(location);
+        }
+      }
+      Err(e) => error = Some(e.message),
+    }
+
+    Purge::update(conn, key, &uuid, |stored| {
+      stored.purge.status = if error.is_none() && failed_files.is_empty() {
+        PurgeStatus::Done
+      } else {
+        PurgeStatus::Failed
+      };
+      stored.purge.finished_ms = Some(time::now_millis());
+      stored.purge.deleted_files = deleted_files;
+      stored.purge.failed_files = failed_files.clone();
+      stored.purge.error = error.clone();
+      stored.table = None;
+      stored.warehouse_root = None;
+    })
+  }
+
+  // Change the purge stored under `key`, unless it was replaced by the purge of another table.
+  fn update(
+    conn: &DBConnection,
+    key: &Key,
+    table_uuid: &str,
+    change: impl Fn(&mut StoredPurge),
+  ) -> Result<()> {
+    conn.transact(|txn| match txn.get::<StoredPurge>(key)? {
+      Some(mut stored) if stored.purge.table_uuid == table_uuid => {
+        change(&mut stored);
+        txn.put(key, &stored)
+      }
+      _ => Ok(()),
+    })
+  }
+}
+
+// Every file of a table, in the order they can be deleted: the files referenced by a file come
+// before it, so an interrupted purge still finds the files left when it starts over.
+fn table_files(table: &Table) -> Result<Vec<String>> {
+  let metadata = &table.metadata;
+  let mut manifest_lists = vec![];
+  let mut manifests = vec![];
+  for snapshot in metadata.snapshots.iter() {
+    if let Some(manifest_list) = &snapshot.manifest_list {
+      for record in read_records(manifest_list)? {
+        if let Some(path) = record["manifest_path"].as_str() {
+          manifests.push(path.to_string());
+        }
+      }
+      manifest_lists.push(manifest_list.clone());
+    }
+    manifests.extend(snapshot.manifests.iter().flatten().cloned());
+  }
+
+  let mut files = vec![];
+  let mut seen = HashSet::new();
+  for manifest in manifests.iter() {
+    if !seen.insert(manifest) {
+      continue;
+    }
+    for record in read_records(manifest)? {
+      if let Some(path) = record["data_file"]["file_path"].as_str() {
+        files.push(path.to_string());
+      }
+    }
+  }
+  files.extend(manifests);
+  files.extend(manifest_lists);
+  files.extend(
+    metadata
+      .statistics
+      .iter()
+      .map(|x| x.statistics_path.clone()),
+  );
+  files.extend(
+    metadata
+      .partition_statistics
+      .iter()
+      .map(|x| x.statistics_path.clone()),
+  );
+  files.extend(
+    metadata
+      .metadata_log
+      .iter()
+      .map(|x| x.metadata_file.clone()),
+  );
+  files.extend(table.metadata_location.clone());
+
+  let mut seen = HashSet::new();
+  files.retain(|file| seen.insert(file.clone()));
+  Ok(files)
+}
+
+// Manifests and manifest lists already deleted have nothing left to purge.
+fn read_records(location: &str) -> Result<Vec<Value>> {
+  match avro::read_records(location) {
+    Err(e) if matches!(e.error_type, ErrorType::NotFound) => Ok(vec![]),
+    result => result,
+  }
+}
+
+// Delete `file` if it is under `location`, after resolving `..` and symbolic links.
+fn remove_file(file: &str, location: Option<&Path>) -> io::Result<()> {
+  let path = match file_io::local_path(file) {
+    Ok(path) => fs::canonicalize(path)?,
+    Err(e) => return Err(io::Error::new(ErrorKind::InvalidInput, e.message)),
+  };
+  match location {
+    Some(location) if path.starts_with(location) => fs::remove_file(path),
+    _ => Err(io::Error::new(
+      ErrorKind::PermissionDenied,
+      format!("{} is outside of the table location", file),
+    )),
+  }
+}
+
+// Remove the directories under `path` left empty by the purge, and `path` itself if it is empty.
+fn remove_empty_dirs(path: &Path) {
+  if let Ok(entries) = fs::read_dir(path) {
+    for entry in entries.flatten() {
+      if entry.file_type().is_ok_and(|x| x.is_dir()) {
+        remove_empty_dirs(&entry.path());
+      }
+    }
+  }
+  // fails if anything else is left in the directory
+  let _ = fs::remove_dir(path);
+}
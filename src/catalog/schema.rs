@@ -0,0 +1,335 @@
+use crate::{
+  common::result::{ErrorType, Location, Result},
+  err,
+  server::routes::common::*,
+};
+use std::collections::{HashMap, HashSet};
+
+// partition field ids start at 1000 in the Iceberg spec
+pub const PARTITION_DATA_ID_START: i32 = 1000;
+
+fn invalid<T>(message: String) -> Result<T> {
+  err!(ErrorType::BadRequest, Location::Table, message)
+}
+
+pub fn max_field_id(type_: &Type) -> i32 {
+  match type_ {
+    Type::Primitive(_) => 0,
+    Type::Struct(s) => s
+      .fields
+      .iter()
+      .map(|f| f.id.max(max_field_id(&f.type_)))
+      .max()
+      .unwrap_or(0),
+    Type::List(l) => l.element_id.max(max_field_id(&l.element)),
+    Type::Map(m) => m
+      .key_id
+      .max(m.value_id)
+      .max(max_field_id(&m.key))
+      .max(max_field_id(&m.value)),
+  }
+}
+
+fn is_valid_primitive(name: &str) -> bool {
+  const PRIMITIVES: [&str; 15] = [
+    "boolean",
+    "int",
+    "long",
+    "float",
+    "double",
+    "date",
+    "time",
+    "timestamp",
+    "timestamptz",
+    "timestamp_ns",
+    "timestamptz_ns",
+    "string",
+    "uuid",
+    "binary",
+    "unknown",
+  ];
+  if PRIMITIVES.contains(&name) {
+    return true;
+  }
+  if let Some(len) = name
+    .strip_prefix("fixed[")
+    .and_then(|x| x.strip_suffix(']'))
+  {
+    return len.trim().parse::<u32>().is_ok();
+  }
+  if let Some(args) = name
+    .strip_prefix("decimal(")
+    .and_then(|x| x.strip_suffix(')'))
+  {
+    let parts: Vec<_> = args.split(',').map(|x| x.trim().parse::<u32>()).collect();
+    return match parts[..] {
+      [Ok(precision), Ok(scale)] => precision <= 38 && scale <= precision,
+      _ => false,
+    };
+  }
+  false
+}
+
+fn validate_type(type_: &Type) -> Result<()> {
+  match type_ {
+    Type::Primitive(p) => {
+      if !is_valid_primitive(&p.0) {
+        return invalid(format!("Invalid primitive type: {}", p.0));
+      }
+    }
+    Type::Struct(s) => {
+      if s.type_ != "struct" {
+        return invalid(format!("Invalid struct type: {}", s.type_));
+      }
+      let mut names = HashSet::new();
+      for field in s.fields.iter() {
+        if field.name.is_empty() {
+          return invalid("Field names cannot be empty".to_string());
+        }
+        if !names.insert(field.name.as_str()) {
+          return invalid(format!("Duplicate field name: {}", field.name));
+        }
+        validate_type(&field.type_)?;
+      }
+    }
+    Type::List(l) => {
+      if l.type_ != "list" {
+        return invalid(format!("Invalid list type: {}", l.type_));
+      }
+      validate_type(&l.element)?;
+    }
+    Type::Map(m) => {
+      if m.type_ != "map" {
+        return invalid(format!("Invalid map type: {}", m.type_));
+      }
+      validate_type(&m.key)?;
+      validate_type(&m.value)?;
+    }
+  }
+  Ok(())
+}
+
+// Look up a field by id, only descending into structs. Fields nested in lists and maps cannot be
+// used as identifier fields.
+fn find_struct_field(fields: &[StructField], id: i32) -> Option<&StructField> {
+  for field in fields {
+    if field.id == id {
+      return Some(field);
+    }
+    if let Type::Struct(s) = &field.type_ {
+      if let Some(found) = find_struct_field(&s.fields, id) {
+        return Some(found);
+      }
+    }
+  }
+  None
+}
+
+// Validate a schema sent by a client. Field ids are not checked since they get reassigned.
+pub fn validate_schema(schema: &Schema) -> Result<()> {
+  validate_type(&Type::Struct(schema.struct_type.clone()))?;
+  for id in schema.identifier_field_ids.iter() {
+    let field = match find_struct_field(&schema.struct_type.fields, *id) {
+      Some(field) => field,
+      None => {
+        return invalid(format!(
+          "Cannot find identifier field {} in the schema (fields nested in lists or maps are not allowed)",
+          id
+        ))
+      }
+    };
+    match &field.type_ {
+      Type::Primitive(p) if p.0 != "float" && p.0 != "double" => {}
+      _ => {
+        return invalid(format!(
+          "Identifier field {} must be a primitive type other than float or double",
+          field.name
+        ))
+      }
+    }
+    if !field.required {
+      return invalid(format!("Identifier field {} must be required", field.name));
+    }
+  }
+  Ok(())
+}
+
+// Maps the ids sent by the client to the freshly assigned ones. Ids that were used more than once
+// by the client map to None since they cannot be resolved.
+pub struct IdMapping(HashMap<i32, Option<i32>>);
+
+impl IdMapping {
+  fn insert(&mut self, old: i32, new: i32) {
+    self
+      .0
+      .entry(old)
+      .and_modify(|x| *x = None)
+      .or_insert(Some(new));
+  }
+
+  pub fn get(&self, old: i32) -> Result<i32> {
+    match self.0.get(&old) {
+      Some(Some(new)) => Ok(*new),
+      Some(None) => invalid(format!("Ambiguous source field id: {}", old)),
+      None => invalid(format!("Cannot find source field {} in the schema", old)),
+    }
+  }
+}
+
+struct FreshIds {
+  next_id: i32,
+  mapping: IdMapping,
+}
+
+impl FreshIds {
+  fn next(&mut self, old: i32) -> i32 {
+    self.next_id += 1;
+    self.mapping.insert(old, self.next_id);
+    self.next_id
+  }
+
+  // Same order as Iceberg's `AssignFreshIds`: all fields of a struct are numbered before any of
+  // their children.
+  fn assign(&mut self, type_: &Type) -> Type {
+    match type_ {
+      Type::Primitive(p) => Type::Primitive(p.clone()),
+      Type::Struct(s) => {
+        let ids: Vec<i32> = s.fields.iter().map(|f| self.next(f.id)).collect();
+        let fields = s
+          .fields
+          .iter()
+          .zip(ids)
+          .map(|(f, id)| StructField {
+            id,
+            name: f.name.clone(),
+            type_: self.assign(&f.type_),
+            required: f.required,
+            doc: f.doc.clone(),
+          })
+          .collect();
+        Type::Struct(StructType {
+          type_: s.type_.clone(),
+          fields,
+        })
+      }
+      Type::List(l) => {
+        let element_id = self.next(l.element_id);
+        Type::List(Box::new(ListType {
+          type_: l.type_.clone(),
+          element_id,
+          element: self.assign(&l.element),
+          element_required: l.element_required,
+        }))
+      }
+      Type::Map(m) => {
+        let key_id = self.next(m.key_id);
+        let value_id = self.next(m.value_id);
+        Type::Map(Box::new(MapType {
+          type_: m.type_.clone(),
+          key_id,
+          key: self.assign(&m.key),
+          value_id,
+          value: self.assign(&m.value),
+          value_required: m.value_required,
+        }))
+      }
+    }
+  }
+}
+
+// Reassign all field ids of the schema starting from 1, the way the Iceberg reference catalogs do
+// for new tables. Returns the new schema, the id mapping and the last assigned column id.
+pub fn assign_fresh_schema_ids(schema: &Schema) -> Result<(Schema, IdMapping, i32)> {
+  let mut fresh = FreshIds {
+    next_id: 0,
+    mapping: IdMapping(HashMap::new()),
+  };
+  let struct_type = match fresh.assign(&Type::Struct(schema.struct_type.clone())) {
+    Type::Struct(s) => s,
+    _ => unreachable!(),
+  };
+  let identifier_field_ids = schema
+    .identifier_field_ids
+    .iter()
+    .map(|id| fresh.mapping.get(*id))
+    .collect::<Result<Vec<_>>>()?;
+  let new_schema = Schema {
+    struct_type,
+    schema_id: 0,
+    identifier_field_ids,
+  };
+  Ok((new_schema, fresh.mapping, fresh.next_id))
+}
+
+fn is_valid_transform(transform: &str) -> bool {
+  const TRANSFORMS: [&str; 7] = [
+    "identity", "year", "month", "day", "hour", "void", "unknown",
+  ];
+  if TRANSFORMS.contains(&transform) {
+    return true;
+  }
+  for prefix in ["bucket[", "truncate["] {
+    if let Some(arg) = transform
+      .strip_prefix(prefix)
+      .and_then(|x| x.strip_suffix(']'))
+    {
+      return arg.trim().parse::<u32>().is_ok_and(|x| x > 0);
+    }
+  }
+  false
+}
+
+// Rebind a partition spec to the freshly assigned schema ids. The new spec always has id 0 and
+// partition field ids starting at 1000.
+pub fn fresh_partition_spec(
+  spec: &PartitionSpec,
+  mapping: &IdMapping,
+) -> Result<(PartitionSpec, i32)> {
+  let mut names = HashSet::new();
+  let mut last_partition_id = PARTITION_DATA_ID_START - 1;
+  let mut fields = vec![];
+  for field in spec.fields.iter() {
+    if !is_valid_transform(&field.transform) {
+      return invalid(format!("Invalid partition transform: {}", field.transform));
+    }
+    if field.name.is_empty() || !names.insert(field.name.as_str()) {
+      return invalid(format!(
+        "Invalid or duplicate partition field name: {}",
+        field.name
+      ));
+    }
+    last_partition_id += 1;
+    fields.push(PartitionField {
+      field_id: Some(last_partition_id),
+      source_id: mapping.get(field.source_id)?,
+      name: field.name.clone(),
+      transform: field.transform.clone(),
+    });
+  }
+  Ok((PartitionSpec { spec_id: 0, fields }, last_partition_id))
+}
+
+// Rebind a sort order to the freshly assigned schema ids. The unsorted order has id 0, any other
+// order gets id 1.
+pub fn fresh_sort_order(order: &SortOrder, mapping: &IdMapping) -> Result<SortOrder> {
+  let mut fields = vec![];
+  for field in order.fields.iter() {
+    if !is_valid_transform(&field.transform) {
+      return invalid(format!("Invalid sort transform: {}", field.transform));
+    }
+    if field.direction != "asc" && field.direction != "desc" {
+      return invalid(format!("Invalid sort direction: {}", field.direction));
+    }
+    if field.null_order != "nulls-first" && field.null_order != "nulls-last" {
+      return invalid(format!("Invalid null order: {}", field.null_order));
+    }
+    fields.push(SortField {
+      source_id: mapping.get(field.source_id)?,
+      transform: field.transform.clone(),
+      direction: field.direction.clone(),
+      null_order: field.null_order.clone(),
+    });
+  }
+  let order_id = if fields.is_empty() { 0 } else { 1 };
+  Ok(SortOrder { order_id, fields })
+}
@@ -1,7 +1,19 @@
 use crate::{
+  catalog::{
+    dropped::Dropped,
+    file_io::{self, MetadataFiles, Warehouse},
+    purge::Purge,
+    schema,
+    update::{invalid_update, resolve_last_added},
+  },
   common::result::{ErrorType, Location, Result},
   err,
   server::routes::common::*,
+  server::routes::request::*,
+  util::{
+    page::{paginate, Page},
+    time,
+  },
 };
 use rocket::{
   serde::{Deserialize, Serialize},
@@ -9,92 +21,268 @@ use rocket::{
 };
 
 // use crate::Location::Namespace; // TODO: update
-use crate::catalog::namespace::Namespace;
+use crate::catalog::namespace::{Namespace, NamespaceIdent};
 
-use crate::db::DBConnection;
+use crate::db::{store::is_conflict, DBConnection, DBRead, Key, Transaction};
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct Table {
   pub name: String,
-  //   pub properties: Value,
-  pub schema: Schema,
   pub metadata: TableMetadata,
+  // location of the metadata file this table was loaded from, if any
+  #[serde(default)]
+  pub metadata_location: Option<String>,
 }
 
 impl Table {
   // exist will not return an error
-  pub fn exists(conn: &DBConnection, namespace_name: String, table_name: String) -> bool {
-    let table_key = format!("{}_{}", namespace_name, table_name);
+  pub fn exists(
+    conn: &impl DBRead,
+    namespace_name: &[NamespaceIdent],
+    table_name: String,
+  ) -> Result<bool> {
+    let table_key = Key::table(namespace_name, &table_name);
     conn.exists(&table_key)
 
     // TODO: probably want to know whether it is namespace not found or table not found
   }
 
+  // Build a new table without registering it in the namespace. This is used by `stage-create`,
+  // the table becomes visible once a commit with `assert-create` is made.
+  pub fn stage(
+    conn: &impl DBRead,
+    namespace: &[NamespaceIdent],
+    request: &CreateTableRequest,
+    table_metedata_generator: &State<TableMetadataGenerator>,
+    warehouse: &Warehouse,
+  ) -> Result<Table> {
+    // add checking for whether namespace exists
+    let namespace_instance = match conn.get::<Namespace>(&Key::namespace(namespace))? {
+      Some(namespace_instance) => namespace_instance,
+      None => {
+        return err!(
+          ErrorType::NotFound,
+          Location::Namespace,
+          format!("Namespace {} not found", Key::namespace(namespace))
+        )
+      }
+    };
+
+    Namespace::check_name_free(conn, namespace, &request.name)?;
+    let mut metadata = Table::new_metadata(request, table_metedata_generator)?;
+    if metadata.location.is_none() {
+      metadata.location = Some(default_location(
+        namespace,
+        &namespace_instance,
+        &request.name,
+        warehouse,
+      ));
+    }
+    check_locations(&metadata, warehouse)?;
+    Ok(Table {
+      name: request.name.clone(),
+      metadata,
+      metadata_location: None,
+    })
+  }
+
   pub fn create(
-    conn: &mut DBConnection,
-    namespace: String,
-    table: String,
-    table_metedata_generator: &State<TableMetadataAtomicIncr>,
+    conn: &DBConnection,
+    namespace: &[NamespaceIdent],
+    request: &CreateTableRequest,
+    table_metedata_generator: &State<TableMetadataGenerator>,
+    warehouse: &Warehouse,
   ) -> Result<Table> {
-    let namespace_key = namespace.clone();
-    let table_key = format!("{}_{}", namespace, table);
-    let table_name = table.clone();
-    let table_clone = table.clone();
+    let mut files = MetadataFiles::default();
+    let result = conn.transact(|txn| {
+      files.new_attempt();
+      let mut new_table =
+        Table::stage(txn, namespace, request, table_metedata_generator, warehouse)?;
+      write_metadata_file(&mut new_table, None, &mut files)?;
+      Table::insert(txn, namespace, &new_table)?;
+      Ok(new_table)
+    });
+    files.finish(result)
+  }
 
-    // add checking for whether namespace exists
-    if !conn.exists(&namespace) {
+  // Register an existing table from its metadata file.
+  pub fn register(
+    conn: &DBConnection,
+    namespace: &[NamespaceIdent],
+    table_name: String,
+    metadata_location: String,
+  ) -> Result<Table> {
+    let metadata = validate_metadata(file_io::read_metadata(&metadata_location)?)?;
+    conn.transact(|txn| {
+      Table::register_in(
+        txn,
+        namespace,
+        table_name.clone(),
+        metadata_location.clone(),
+        metadata.clone(),
+      )
+    })
+  }
+
+  fn register_in(
+    txn: &mut Transaction,
+    namespace: &[NamespaceIdent],
+    table_name: String,
+    metadata_location: String,
+    metadata: TableMetadata,
+  ) -> Result<Table> {
+    if !Namespace::exists(txn, namespace)? {
       return err!(
         ErrorType::NotFound,
-        Location::Namespace, // ??
-        format!("Namespace {} not found", namespace)
+        Location::Namespace,
+        format!("Namespace {} not found", Key::namespace(namespace))
       );
     }
+    Namespace::check_name_free(txn, namespace, &table_name)?;
 
-    if Table::exists(conn, namespace, table) {
-      return err!(
-        ErrorType::AlreadyExists,
-        Location::Table,
-        format!("Table {} already exists", table_key)
-      );
-    }
-    let new_table = Table {
+    let table = Table {
       name: table_name,
-      schema: Schema {
-        // struct_type: StructType {
-        //   type_: "".to_string(),
-        //   fields: vec![],
-        //   // Populate StructType fields as needed
-        // },
-        schema_id: 0,                 // Set schema_id to a valid value
-        identifier_field_ids: vec![], // Provide identifier_field_ids if needed
-      },
-      metadata: table_metedata_generator.generate_table_metadata(1),
+      metadata,
+      metadata_location: Some(metadata_location),
     };
-    conn.put(&table_key, &new_table)?;
+    Table::insert(txn, namespace, &table)?;
+    Ok(table)
+  }
+
+  // Store a new table and add it to the namespace tables.
+  fn insert(txn: &mut Transaction, namespace: &[NamespaceIdent], table: &Table) -> Result<()> {
+    let table_key = Key::table(namespace, &table.name);
+    txn.put(&table_key, table)?;
 
     // add the table to the namespace tables
-    if let Some(mut namespace_instance) = conn.get::<Namespace>(&namespace_key) {
-      namespace_instance.tables.push(table_clone.clone());
-      conn.put(&namespace_key, &namespace_instance)?;
+    let namespace_key = Key::namespace(namespace);
+    if let Some(mut namespace_instance) = txn.get::<Namespace>(&namespace_key)? {
+      namespace_instance.tables.push(table.name.clone());
+      txn.put(&namespace_key, &namespace_instance)?;
     }
-
-    Ok(new_table)
+    Ok(())
   }
 
-  pub fn delete(conn: &mut DBConnection, namespace: String, table: String) -> Result<()> {
-    let table_key = format!("{}_{}", namespace, table);
-    let table_name = table.clone();
-    if !Table::exists(conn, namespace.clone(), table) {
+  // Build the metadata of a new table from a create request. Field ids of the schema are
+  // reassigned and the partition spec and sort order are rebound to the new ids.
+  fn new_metadata(
+    request: &CreateTableRequest,
+    table_metedata_generator: &State<TableMetadataGenerator>,
+  ) -> Result<TableMetadata> {
+    if request.name.is_empty() {
       return err!(
-        ErrorType::NotFound,
+        ErrorType::BadRequest,
         Location::Table,
-        format!("Table {} not found", table_key)
+        "Table name cannot be empty".to_string()
       );
     }
+    schema::validate_schema(&request.schema)?;
+    let (fresh_schema, mapping, last_column_id) = schema::assign_fresh_schema_ids(&request.schema)?;
+    let empty_spec = PartitionSpec {
+      spec_id: 0,
+      fields: vec![],
+    };
+    let (spec, last_partition_id) = schema::fresh_partition_spec(
+      request.partition_spec.as_ref().unwrap_or(&empty_spec),
+      &mapping,
+    )?;
+    let unsorted = SortOrder {
+      order_id: 0,
+      fields: vec![],
+    };
+    let sort_order =
+      schema::fresh_sort_order(request.write_order.as_ref().unwrap_or(&unsorted), &mapping)?;
+
+    // `format-version` is a reserved property that picks the format version of the new table
+    let mut properties = request.properties.clone().unwrap_or_default();
+    let format_version = match properties.remove(FORMAT_VERSION_PROPERTY) {
+      None => DEFAULT_FORMAT_VERSION,
+      Some(v) => match v.parse::<i32>() {
+        Ok(v) if (1..=2).contains(&v) => v,
+        _ => {
+          return err!(
+            ErrorType::BadRequest,
+            Location::Table,
+            format!("Unsupported format version: {}", v)
+          )
+        }
+      },
+    };
+
+    let mut metadata = table_metedata_generator.generate_table_metadata(format_version);
+    metadata.location = request.location.clone();
+    metadata.last_updated_ms = Some(time::now_millis());
+    metadata.properties = properties;
+    metadata.schemas = vec![fresh_schema];
+    metadata.current_schema_id = Some(0);
+    metadata.last_column_id = Some(last_column_id);
+    metadata.partition_specs = vec![spec];
+    metadata.default_spec_id = Some(0);
+    metadata.last_partition_id = Some(last_partition_id);
+    metadata.default_sort_order_id = Some(sort_order.order_id);
+    metadata.sort_orders = vec![sort_order];
+    metadata.last_sequence_number = Some(0);
+    Ok(metadata)
+  }
+
+  // Drop a table. When `purge_requested`, a pending purge of its files is recorded with the drop,
+  // see `Purge::start`. Otherwise the table is kept for `retention_ms` so it can be restored, and
+  // its drop is returned.
+  pub fn delete(
+    conn: &DBConnection,
+    namespace: &[NamespaceIdent],
+    table: String,
+    purge_requested: bool,
+    retention_ms: i64,
+    warehouse: &Warehouse,
+  ) -> Result<Option<Dropped>> {
+    conn.transact(|txn| {
+      Table::delete_in(
+        txn,
+        namespace,
+        table.clone(),
+        purge_requested,
+        retention_ms,
+        warehouse,
+      )
+    })
+  }
+
+  fn delete_in(
+    txn: &mut Transaction,
+    namespace: &[NamespaceIdent],
+    table: String,
+    purge_requested: bool,
+    retention_ms: i64,
+    warehouse: &Warehouse,
+  ) -> Result<Option<Dropped>> {
+    let table_key = Key::table(namespace, &table);
+    let table_name = table.clone();
+    let dropped = match Table::get(txn, namespace, table)? {
+      Some(dropped) => dropped,
+      None => {
+        return err!(
+          ErrorType::NotFound,
+          Location::Table,
+          format!("Table {} not found", table_key)
+        )
+      }
+    };
+    let dropped = if purge_requested {
+      Purge::request(txn, namespace, dropped, warehouse)?;
+      None
+    } else {
+      Some(Dropped::retain_table(
+        txn,
+        namespace,
+        dropped,
+        retention_ms,
+      )?)
+    };
 
-    let namespace_key = namespace.clone();
-    if let Some(mut namespace_instance) = conn.get::<Namespace>(&namespace_key) {
+    let namespace_key = Key::namespace(namespace);
+    if let Some(mut namespace_instance) = txn.get::<Namespace>(&namespace_key)? {
       // Remove the table from the namespace's tables vector
       if let Some(index) = namespace_instance
         .tables
@@ -102,80 +290,914 @@ impl Table {
         .position(|t| t == &table_name)
       {
         namespace_instance.tables.remove(index);
-        conn.put(&namespace_key, &namespace_instance)?;
+        txn.put(&namespace_key, &namespace_instance)?;
       }
     }
 
-    conn.delete(&table_key)
+    txn.delete(&table_key);
+    Ok(dropped)
   }
 
-  pub fn list(conn: &DBConnection, namespace: String) -> Option<Vec<String>> {
-    let key = namespace;
-    // let namespace_instance = conn.get(key);
-    // let tables = namespace_instance.tables;
+  // Add a dropped table back to its namespace under its old name.
+  pub fn restore_in(
+    txn: &mut Transaction,
+    namespace: &[NamespaceIdent],
+    table: Table,
+  ) -> Result<()> {
+    let namespace_key = Key::namespace(namespace);
+    let mut namespace_instance: Namespace = match txn.get(&namespace_key)? {
+      Some(namespace_instance) => namespace_instance,
+      None => {
+        return err!(
+          ErrorType::NotFound,
+          Location::Namespace,
+          format!("Namespace {} not found", namespace_key)
+        )
+      }
+    };
+    Namespace::check_name_free(txn, namespace, &table.name)?;
+    namespace_instance.tables.push(table.name.clone());
+    txn.put(&namespace_key, &namespace_instance)?;
+    txn.put(&Key::table(namespace, &table.name), &table)
+  }
 
-    // add checking for whether namespace exists
-    // let namespace_clone = namespace.clone();
-    // if !conn.exists(&namespace_clone){
-    //   return err!(
-    //       ErrorType::NotFound,
-    //       Location::Namespace,
-    //       format!("Namespace {} not found", namespace_clone)
-    //   );
-    // }
-
-    if let Some(namespace_instance) = conn.get::<Namespace>(&key) {
-      Some(namespace_instance.tables.clone())
-    } else {
-      None
+  // List the tables of a namespace, a page at a time (see `paginate`).
+  pub fn list(
+    conn: &impl DBRead,
+    namespace: &[NamespaceIdent],
+    page: &PageRequest,
+  ) -> Result<Page<String>> {
+    let key = Key::namespace(namespace);
+    match conn.get::<Namespace>(&key)? {
+      Some(namespace_instance) => paginate(namespace_instance.tables, page),
+      None => err!(
+        ErrorType::NotFound,
+        Location::Namespace,
+        format!("Namespace {} not found", key)
+      ),
     }
-    // Some(tables)
   }
 
-  pub fn get(conn: &DBConnection, namespace_name: String, table_name: String) -> Option<Table> {
-    let table_key = format!("{}_{}", namespace_name, table_name);
-    if let Some(table_instance) = conn.get::<Table>(&table_key) {
-      Some(table_instance)
-    } else {
-      None
-    }
+  pub fn get(
+    conn: &impl DBRead,
+    namespace_name: &[NamespaceIdent],
+    table_name: String,
+  ) -> Result<Option<Table>> {
+    let table_key = Key::table(namespace_name, &table_name);
+    conn.get::<Table>(&table_key)
 
     // TODO: probably want to know whether it is namespace not found or table not found
   }
 
+  // Check every requirement against the current metadata and apply all the updates. Nothing is
+  // written unless all requirements hold and all updates apply cleanly.
+  pub fn commit(
+    conn: &DBConnection,
+    namespace_name: &[NamespaceIdent],
+    table_name: String,
+    requirements: &[TableRequirement],
+    updates: Vec<TableUpdate>,
+    table_metedata_generator: &State<TableMetadataGenerator>,
+    warehouse: &Warehouse,
+  ) -> Result<Table> {
+    // The table is compared and swapped: when another commit stored a new version of the table
+    // (a new metadata location) after this one read it, nothing is written and the client gets a
+    // CommitFailed to retry from the new metadata.
+    let mut files = MetadataFiles::default();
+    let result = conn.try_transact(|txn| {
+      Table::commit_in(
+        txn,
+        namespace_name,
+        table_name.clone(),
+        requirements,
+        updates,
+        table_metedata_generator,
+        warehouse,
+        &mut files,
+      )
+    });
+    match files.finish(result) {
+      Err(e) if is_conflict(&e) => err!(
+        ErrorType::CommitFailed,
+        Location::Table,
+        format!(
+          "Table {} was changed by a concurrent commit",
+          Key::table(namespace_name, &table_name)
+        )
+      ),
+      result => result,
+    }
+  }
+
+  // Commit the changes of several tables at once: either the requirements of every change hold
+  // and all the tables are updated, or none is. Like a single commit, a change of any of the
+  // tables by a concurrent commit fails the whole transaction with a CommitFailed.
+  pub fn commit_transaction(
+    conn: &DBConnection,
+    table_changes: Vec<CommitTableRequest>,
+    table_metedata_generator: &State<TableMetadataGenerator>,
+    warehouse: &Warehouse,
+  ) -> Result<()> {
+    let mut changes = Vec::with_capacity(table_changes.len());
+    for change in table_changes {
+      let identifier = match change.identifier {
+        Some(identifier) => identifier,
+        None => {
+          return err!(
+            ErrorType::BadRequest,
+            Location::Table,
+            "Every table change of a transaction needs an identifier".to_string()
+          )
+        }
+      };
+      changes.push((
+        identifier.namespace.0,
+        identifier.name,
+        change.requirements,
+        change.updates,
+      ));
+    }
+    let mut files = MetadataFiles::default();
+    let result = conn.try_transact(|txn| {
+      for (namespace_name, table_name, requirements, updates) in changes {
+        Table::commit_in(
+          txn,
+          &namespace_name,
+          table_name,
+          &requirements,
+          updates,
+          table_metedata_generator,
+          warehouse,
+          &mut files,
+        )?;
+      }
+      Ok(())
+    });
+    match files.finish(result) {
+      Err(e) if is_conflict(&e) => err!(
+        ErrorType::CommitFailed,
+        Location::Table,
+        "A table of the transaction was changed by a concurrent commit".to_string()
+      ),
+      result => result,
+    }
+  }
+
+  #[allow(clippy::too_many_arguments)]
+  fn commit_in(
+    txn: &mut Transaction,
+    namespace_name: &[NamespaceIdent],
+    table_name: String,
+    requirements: &[TableRequirement],
+    updates: Vec<TableUpdate>,
+    table_metedata_generator: &State<TableMetadataGenerator>,
+    warehouse: &Warehouse,
+    files: &mut MetadataFiles,
+  ) -> Result<Table> {
+    let table_key = Key::table(namespace_name, &table_name);
+    let current = txn.get::<Table>(&table_key)?;
+    let is_create = requirements
+      .iter()
+      .any(|r| matches!(r.requirement, RequirementType::AssertCreate));
+    if current.is_none() && !is_create {
+      return err!(
+        ErrorType::NotFound,
+        Location::Table,
+        format!("Table {} not found", table_key)
+      );
+    }
+    if current.is_none() && !Namespace::exists(txn, namespace_name)? {
+      return err!(
+        ErrorType::NotFound,
+        Location::Namespace,
+        format!("Namespace {} not found", Key::namespace(namespace_name))
+      );
+    }
+    if current.is_none() {
+      Namespace::check_name_free(txn, namespace_name, &table_name)?;
+    }
+
+    for requirement in requirements {
+      check_requirement(
+        &requirement.requirement,
+        current.as_ref().map(|t| &t.metadata),
+      )?;
+    }
+
+    match current {
+      Some(mut table) => {
+        let mut metadata = table.metadata.clone();
+        apply_updates(&mut metadata, updates)?;
+        check_locations(&metadata, warehouse)?;
+        let previous_updated_ms = table.metadata.last_updated_ms;
+        table.metadata = metadata;
+        write_metadata_file(&mut table, previous_updated_ms, files)?;
+        txn.put(&table_key, &table)?;
+        Ok(table)
+      }
+      None => {
+        // create transaction: the metadata is built from scratch by the updates
+        let mut metadata = table_metedata_generator.generate_table_metadata(1);
+        metadata.last_partition_id = Some(schema::PARTITION_DATA_ID_START - 1);
+        metadata.last_sequence_number = Some(0);
+        apply_updates(&mut metadata, updates)?;
+        if metadata.current_schema_id.is_none() {
+          return err!(
+            ErrorType::BadRequest,
+            Location::Table,
+            "Cannot create a table without a current schema".to_string()
+          );
+        }
+        if metadata.location.is_none() {
+          if let Some(namespace) = txn.get::<Namespace>(&Key::namespace(namespace_name))? {
+            metadata.location = Some(default_location(
+              namespace_name,
+              &namespace,
+              &table_name,
+              warehouse,
+            ));
+          }
+        }
+        check_locations(&metadata, warehouse)?;
+        let mut table = Table {
+          name: table_name,
+          metadata,
+          metadata_location: None,
+        };
+        write_metadata_file(&mut table, None, files)?;
+        Table::insert(txn, namespace_name, &table)?;
+        Ok(table)
+      }
+    }
+  }
+
+  // Older versions of the catalog generated table uuids from an in-memory counter (`uuid0`,
+  // `uuid1`, ...) that restarted from 0 with the process, so the same uuid can belong to several
+  // tables. Give every such table a random uuid and a new metadata file, once: the migration is
+  // marked as done so later starts skip the scan of the catalog.
+  pub fn migrate_uuids(conn: &DBConnection) -> Result<()> {
+    let migration = Key::Migration("table-uuids".to_string());
+    if conn.exists(&migration)? {
+      return Ok(());
+    }
+    for key in conn.keys() {
+      if !matches!(key, Key::Table(..)) {
+        continue;
+      }
+      let mut table = match conn.get::<Table>(&key)? {
+        Some(table) if uuid::Uuid::parse_str(&table.metadata.table_uuid).is_err() => table,
+        _ => continue,
+      };
+      let previous_updated_ms = table.metadata.last_updated_ms;
+      table.metadata.table_uuid = uuid::Uuid::new_v4().to_string();
+      table.metadata.last_updated_ms = Some(time::now_millis());
+      let mut files = MetadataFiles::default();
+      write_metadata_file(&mut table, previous_updated_ms, &mut files)?;
+      files.finish(conn.put(&key, &table))?;
+    }
+    conn.put(&migration, &true)
+  }
+
+  // Move a table to a new name, possibly in another namespace. The table keeps its metadata, the
+  // source namespace loses it and the destination namespace gains it in the same transaction.
   pub fn rename(
-    conn: &mut DBConnection,
-    namespace_name: String,
-    old_table_name: String,
-    new_table_name: String,
-  ) -> Result<bool> {
-    let old_table_key = format!("{}_{}", namespace_name.clone(), old_table_name.clone());
-    let new_table_key = format!("{}_{}", namespace_name.clone(), new_table_name.clone());
+    conn: &DBConnection,
+    source_namespace: &[NamespaceIdent],
+    source_name: String,
+    destination_namespace: &[NamespaceIdent],
+    destination_name: String,
+  ) -> Result<()> {
+    conn.transact(|txn| {
+      Table::rename_in(
+        txn,
+        source_namespace,
+        &source_name,
+        destination_namespace,
+        &destination_name,
+      )
+    })
+  }
 
-    if let Some(mut old_table) = conn.get::<Table>(&old_table_key) {
-      old_table.name = new_table_name.clone();
-      conn.put(&new_table_key, &old_table)?;
-      conn.delete(&old_table_key)?; // Remove the old key
-    } else {
-      return Ok(false); // If the old table does not exist, return false or handle it accordingly
+  fn rename_in(
+    txn: &mut Transaction,
+    source_namespace: &[NamespaceIdent],
+    source_name: &str,
+    destination_namespace: &[NamespaceIdent],
+    destination_name: &str,
+  ) -> Result<()> {
+    Namespace::move_entry(
+      txn,
+      Location::Table,
+      source_namespace,
+      source_name,
+      destination_namespace,
+      destination_name,
+      |table: &mut Table| table.name = destination_name.to_string(),
+    )
+  }
+}
+
+// `metadata` is None when the table does not exist yet.
+pub fn check_requirement(
+  requirement: &RequirementType,
+  metadata: Option<&TableMetadata>,
+) -> Result<()> {
+  let metadata = match (requirement, metadata) {
+    (RequirementType::AssertCreate, None) => return Ok(()),
+    (RequirementType::AssertCreate, Some(_)) => {
+      return err!(
+        ErrorType::CommitFailed,
+        Location::Table,
+        "Requirement failed: table already exists".to_string()
+      )
+    }
+    (_, None) => {
+      return err!(
+        ErrorType::CommitFailed,
+        Location::Table,
+        "Requirement failed: table does not exist".to_string()
+      )
     }
+    (_, Some(metadata)) => metadata,
+  };
 
-    // true
-    let namespace_key = namespace_name.clone();
-    if let Some(mut namespace) = conn.get::<Namespace>(&namespace_key) {
-      if let Some(index) = namespace
-        .tables
-        .iter()
-        .position(|name| name == &old_table_name)
-      {
-        namespace.tables[index] = new_table_name.clone();
-        conn.put(&namespace_key, &namespace)?;
-        Ok(true)
-      } else {
-        return Ok(false); // If the old table name is not found in the tables vector, return false or handle it accordingly
+  let failure = match requirement {
+    RequirementType::AssertCreate => None,
+    RequirementType::AssertTableUUID(r) => (metadata.table_uuid != r.uuid).then(|| {
+      format!(
+        "table uuid {} does not match {}",
+        metadata.table_uuid, r.uuid
+      )
+    }),
+    RequirementType::AssertRefSnapshotId(r) => {
+      let current = metadata.refs.get(&r.ref_).map(|x| x.snapshot_id);
+      match (current, r.snapshot_id) {
+        (None, None) => None,
+        (Some(_), None) => Some(format!("branch or tag {} was created concurrently", r.ref_)),
+        (None, Some(expected)) => Some(format!(
+          "branch or tag {} is missing, expected {}",
+          r.ref_, expected
+        )),
+        (Some(current), Some(expected)) => (current != expected).then(|| {
+          format!(
+            "branch or tag {} has changed: expected id {} != {}",
+            r.ref_, expected, current
+          )
+        }),
       }
-    } else {
-      return Ok(false); // If the namespace does not exist, return false or handle it accordingly
     }
+    RequirementType::AssertLastAssignedFieldId(r) => {
+      (metadata.last_column_id != Some(r.last_assigned_field_id)).then(|| {
+        format!(
+          "last assigned field id changed: expected id {} != {:?}",
+          r.last_assigned_field_id, metadata.last_column_id
+        )
+      })
+    }
+    RequirementType::AssertCurrentSchemaId(r) => {
+      (metadata.current_schema_id != Some(r.current_schema_id)).then(|| {
+        format!(
+          "current schema changed: expected id {} != {:?}",
+          r.current_schema_id, metadata.current_schema_id
+        )
+      })
+    }
+    RequirementType::AssertLastAssignedPartitionId(r) => {
+      (metadata.last_partition_id != Some(r.last_assigned_partition_id)).then(|| {
+        format!(
+          "last assigned partition id changed: expected id {} != {:?}",
+          r.last_assigned_partition_id, metadata.last_partition_id
+        )
+      })
+    }
+    RequirementType::AssertDefaultSpecId(r) => {
+      (metadata.default_spec_id != Some(r.default_spec_id)).then(|| {
+        format!(
+          "default partition spec changed: expected id {} != {:?}",
+          r.default_spec_id, metadata.default_spec_id
+        )
+      })
+    }
+    RequirementType::AssertDefaultSortOrderId(r) => {
+      (metadata.default_sort_order_id != Some(r.default_sort_order_id)).then(|| {
+        format!(
+          "default sort order changed: expected id {} != {:?}",
+          r.default_sort_order_id, metadata.default_sort_order_id
+        )
+      })
+    }
+  };
+
+  match failure {
+    None => Ok(()),
+    Some(message) => err!(
+      ErrorType::CommitFailed,
+      Location::Table,
+      format!("Requirement failed: {}", message)
+    ),
+  }
+}
+
+// Apply the updates in order, following the rules of the Iceberg `TableMetadata` builder.
+pub fn apply_updates(metadata: &mut TableMetadata, updates: Vec<TableUpdate>) -> Result<()> {
+  let mut last_added_schema_id = None;
+  let mut last_added_spec_id = None;
+  let mut last_added_order_id = None;
+
+  for update in updates {
+    match update {
+      TableUpdate::AssignUUID(u) => metadata.table_uuid = u.uuid,
+      TableUpdate::UpgradeFormatVersion(u) => {
+        if u.format_version > 2 {
+          return invalid_update(
+            Location::Table,
+            format!(
+              "Cannot upgrade table to unsupported format version v{}",
+              u.format_version
+            ),
+          );
+        }
+        if u.format_version < metadata.format_version {
+          return invalid_update(
+            Location::Table,
+            format!(
+              "Cannot downgrade v{} table to v{}",
+              metadata.format_version, u.format_version
+            ),
+          );
+        }
+        metadata.format_version = u.format_version;
+      }
+      TableUpdate::AddSchema(u) => {
+        let mut schema = u.schema;
+        let last_column_id = metadata
+          .last_column_id
+          .unwrap_or(0)
+          .max(u.last_column_id.unwrap_or(0))
+          .max(schema::max_field_id(&Type::Struct(
+            schema.struct_type.clone(),
+          )));
+        let existing = metadata.schemas.iter().find(|s| {
+          s.struct_type == schema.struct_type
+            && s.identifier_field_ids == schema.identifier_field_ids
+        });
+        let schema_id = match existing {
+          Some(s) => s.schema_id,
+          None => {
+            let schema_id = metadata
+              .schemas
+              .iter()
+              .map(|s| s.schema_id + 1)
+              .max()
+              .unwrap_or(0);
+            schema.schema_id = schema_id;
+            metadata.schemas.push(schema);
+            schema_id
+          }
+        };
+        metadata.last_column_id = Some(last_column_id);
+        last_added_schema_id = Some(schema_id);
+      }
+      TableUpdate::SetCurrentSchema(u) => {
+        let schema_id =
+          resolve_last_added(Location::Table, u.schema_id, last_added_schema_id, "schema")?;
+        if !metadata.schemas.iter().any(|s| s.schema_id == schema_id) {
+          return invalid_update(
+            Location::Table,
+            format!("Cannot set current schema to unknown schema: {}", schema_id),
+          );
+        }
+        metadata.current_schema_id = Some(schema_id);
+      }
+      TableUpdate::AddPartitionSpec(u) => {
+        let mut spec = u.spec;
+        let mut last_partition_id = metadata
+          .last_partition_id
+          .unwrap_or(schema::PARTITION_DATA_ID_START - 1);
+        for field in spec.fields.iter_mut() {
+          let field_id = match field.field_id {
+            Some(field_id) => field_id,
+            None => last_partition_id + 1,
+          };
+          field.field_id = Some(field_id);
+          last_partition_id = last_partition_id.max(field_id);
+        }
+        let existing = metadata
+          .partition_specs
+          .iter()
+          .find(|s| s.fields == spec.fields);
+        let spec_id = match existing {
+          Some(s) => s.spec_id,
+          None => {
+            let spec_id = metadata
+              .partition_specs
+              .iter()
+              .map(|s| s.spec_id + 1)
+              .max()
+              .unwrap_or(0);
+            spec.spec_id = spec_id;
+            metadata.partition_specs.push(spec);
+            spec_id
+          }
+        };
+        metadata.last_partition_id = Some(last_partition_id);
+        last_added_spec_id = Some(spec_id);
+      }
+      TableUpdate::SetDefaultSpec(u) => {
+        let spec_id = resolve_last_added(
+          Location::Table,
+          u.spec_id,
+          last_added_spec_id,
+          "partition spec",
+        )?;
+        if !metadata
+          .partition_specs
+          .iter()
+          .any(|s| s.spec_id == spec_id)
+        {
+          return invalid_update(
+            Location::Table,
+            format!(
+              "Cannot set default partition spec to unknown spec: {}",
+              spec_id
+            ),
+          );
+        }
+        metadata.default_spec_id = Some(spec_id);
+      }
+      TableUpdate::AddSortOrder(u) => {
+        let mut order = u.sort_order;
+        let existing = metadata
+          .sort_orders
+          .iter()
+          .find(|o| o.fields == order.fields);
+        let order_id = match existing {
+          Some(o) => o.order_id,
+          None => {
+            // the unsorted order is always id 0
+            let order_id = if order.fields.is_empty() {
+              0
+            } else {
+              metadata
+                .sort_orders
+                .iter()
+                .map(|o| o.order_id + 1)
+                .max()
+                .unwrap_or(1)
+                .max(1)
+            };
+            order.order_id = order_id;
+            metadata.sort_orders.push(order);
+            order_id
+          }
+        };
+        last_added_order_id = Some(order_id);
+      }
+      TableUpdate::SetDefaultSortOrder(u) => {
+        let order_id = resolve_last_added(
+          Location::Table,
+          u.sort_order_id,
+          last_added_order_id,
+          "sort order",
+        )?;
+        if !metadata.sort_orders.iter().any(|o| o.order_id == order_id) {
+          return invalid_update(
+            Location::Table,
+            format!(
+              "Cannot set default sort order to unknown order: {}",
+              order_id
+            ),
+          );
+        }
+        metadata.default_sort_order_id = Some(order_id);
+      }
+      TableUpdate::AddSnapshot(u) => {
+        let snapshot = u.snapshot;
+        if metadata
+          .snapshots
+          .iter()
+          .any(|s| s.snapshot_id == snapshot.snapshot_id)
+        {
+          return invalid_update(
+            Location::Table,
+            format!("Snapshot already exists for id: {}", snapshot.snapshot_id),
+          );
+        }
+        let last_sequence_number = metadata.last_sequence_number.unwrap_or(0);
+        if metadata.format_version >= 2 {
+          let sequence_number = snapshot.sequence_number.unwrap_or(0);
+          if sequence_number <= last_sequence_number && snapshot.parent_snapshot_id.is_some() {
+            return invalid_update(
+              Location::Table,
+              format!(
+                "Cannot add snapshot with sequence number {} older than last sequence number {}",
+                sequence_number, last_sequence_number
+              ),
+            );
+          }
+        }
+        metadata.last_sequence_number =
+          Some(last_sequence_number.max(snapshot.sequence_number.unwrap_or(0)));
+        metadata.snapshots.push(snapshot);
+      }
+      TableUpdate::SetSnapshotRef(u) => {
+        let reference = u.reference;
+        let snapshot = match metadata
+          .snapshots
+          .iter()
+          .find(|s| s.snapshot_id == reference.snapshot_id)
+        {
+          Some(snapshot) => snapshot,
+          None => {
+            return invalid_update(
+              Location::Table,
+              format!(
+                "Cannot set {} to unknown snapshot: {}",
+                u.ref_name, reference.snapshot_id
+              ),
+            )
+          }
+        };
+        if u.ref_name == MAIN_BRANCH && metadata.current_snapshot_id != Some(reference.snapshot_id)
+        {
+          metadata.snapshot_log.push(SnapshotLogEntry {
+            snapshot_id: snapshot.snapshot_id,
+            timestamp_ms: snapshot.timestamp_ms,
+          });
+          metadata.current_snapshot_id = Some(reference.snapshot_id);
+        }
+        metadata.refs.insert(u.ref_name, reference);
+      }
+      TableUpdate::RemoveSnapshots(u) => {
+        metadata
+          .snapshots
+          .retain(|s| !u.snapshot_ids.contains(&s.snapshot_id));
+        // refs pointing to removed snapshots are removed as well
+        let removed_refs: Vec<String> = metadata
+          .refs
+          .iter()
+          .filter(|(_, r)| u.snapshot_ids.contains(&r.snapshot_id))
+          .map(|(name, _)| name.clone())
+          .collect();
+        for name in removed_refs {
+          remove_ref(metadata, &name);
+        }
+        // the snapshot log is only kept after the last entry that refers to a removed snapshot
+        if let Some(index) = metadata
+          .snapshot_log
+          .iter()
+          .rposition(|e| u.snapshot_ids.contains(&e.snapshot_id))
+        {
+          metadata.snapshot_log.drain(..=index);
+        }
+      }
+      TableUpdate::RemoveSnapshotRef(u) => remove_ref(metadata, &u.ref_name),
+      TableUpdate::SetLocation(u) => metadata.location = Some(u.location),
+      TableUpdate::SetProperties(u) => metadata.properties.extend(u.updates),
+      TableUpdate::RemoveProperties(u) => {
+        for key in u.removals {
+          metadata.properties.remove(&key);
+        }
+      }
+    }
+  }
+
+  metadata.last_updated_ms = Some(time::now_millis());
+  Ok(())
+}
+
+fn invalid_metadata<T>(message: String) -> Result<T> {
+  err!(
+    ErrorType::BadRequest,
+    Location::Table,
+    format!("Invalid table metadata: {}", message)
+  )
+}
+
+// Validate metadata read from a metadata file, and normalize the v1 fields the same way the
+// Iceberg metadata parser does.
+pub fn validate_metadata(mut metadata: TableMetadata) -> Result<TableMetadata> {
+  if !(1..=2).contains(&metadata.format_version) {
+    return invalid_metadata(format!(
+      "unsupported format version {}",
+      metadata.format_version
+    ));
+  }
+  if metadata.table_uuid.is_empty() {
+    return invalid_metadata("missing table-uuid".to_string());
+  }
+  if metadata.format_version == 2 {
+    let missing = [
+      ("location", metadata.location.is_none()),
+      (
+        "last-sequence-number",
+        metadata.last_sequence_number.is_none(),
+      ),
+      ("last-updated-ms", metadata.last_updated_ms.is_none()),
+      ("last-column-id", metadata.last_column_id.is_none()),
+      ("schemas", metadata.schemas.is_empty()),
+      ("current-schema-id", metadata.current_schema_id.is_none()),
+      ("partition-specs", metadata.partition_specs.is_empty()),
+      ("default-spec-id", metadata.default_spec_id.is_none()),
+      ("last-partition-id", metadata.last_partition_id.is_none()),
+      ("sort-orders", metadata.sort_orders.is_empty()),
+      (
+        "default-sort-order-id",
+        metadata.default_sort_order_id.is_none(),
+      ),
+    ];
+    if let Some((field, _)) = missing.iter().find(|(_, missing)| *missing) {
+      return invalid_metadata(format!("missing {} in a v2 table", field));
+    }
+  }
+
+  if metadata.schemas.is_empty() {
+    match metadata.schema.clone() {
+      Some(schema) => {
+        metadata.current_schema_id = Some(schema.schema_id);
+        metadata.schemas = vec![schema];
+      }
+      None => return invalid_metadata("missing schemas".to_string()),
+    }
+  }
+  if metadata.partition_specs.is_empty() {
+    metadata.partition_specs = vec![PartitionSpec {
+      spec_id: 0,
+      fields: metadata.partition_spec.clone().unwrap_or_default(),
+    }];
+    metadata.default_spec_id = Some(0);
+  }
+  if metadata.sort_orders.is_empty() {
+    metadata.sort_orders = vec![SortOrder {
+      order_id: 0,
+      fields: vec![],
+    }];
+    metadata.default_sort_order_id = Some(0);
+  }
+  if metadata.last_partition_id.is_none() {
+    let last_partition_id = metadata
+      .partition_specs
+      .iter()
+      .flat_map(|s| s.fields.iter().filter_map(|f| f.field_id))
+      .max()
+      .unwrap_or(schema::PARTITION_DATA_ID_START - 1);
+    metadata.last_partition_id = Some(last_partition_id);
+  }
+  if metadata.last_sequence_number.is_none() {
+    metadata.last_sequence_number = Some(0);
+  }
+
+  let schema_id = metadata.current_schema_id.unwrap_or(0);
+  if !metadata.schemas.iter().any(|s| s.schema_id == schema_id) {
+    return invalid_metadata(format!("current schema {} is not in schemas", schema_id));
+  }
+  metadata.current_schema_id = Some(schema_id);
+  let spec_id = metadata.default_spec_id.unwrap_or(0);
+  if !metadata
+    .partition_specs
+    .iter()
+    .any(|s| s.spec_id == spec_id)
+  {
+    return invalid_metadata(format!(
+      "default spec {} is not in partition-specs",
+      spec_id
+    ));
+  }
+  metadata.default_spec_id = Some(spec_id);
+  let order_id = metadata.default_sort_order_id.unwrap_or(0);
+  if !metadata.sort_orders.iter().any(|o| o.order_id == order_id) {
+    return invalid_metadata(format!(
+      "default sort order {} is not in sort-orders",
+      order_id
+    ));
+  }
+  metadata.default_sort_order_id = Some(order_id);
+
+  // -1 is used by older writers for a table without snapshots
+  if metadata.current_snapshot_id == Some(-1) {
+    metadata.current_snapshot_id = None;
+  }
+  let snapshot_exists = |id: i64| metadata.snapshots.iter().any(|s| s.snapshot_id == id);
+  if let Some(id) = metadata.current_snapshot_id {
+    if !snapshot_exists(id) {
+      return invalid_metadata(format!("current snapshot {} is not in snapshots", id));
+    }
+  }
+  if let Some((name, r)) = metadata
+    .refs
+    .iter()
+    .find(|(_, r)| !snapshot_exists(r.snapshot_id))
+  {
+    return invalid_metadata(format!(
+      "ref {} points to unknown snapshot {}",
+      name, r.snapshot_id
+    ));
+  }
+  // tables written before refs existed only track the current snapshot
+  if let (Some(id), false) = (
+    metadata.current_snapshot_id,
+    metadata.refs.contains_key(MAIN_BRANCH),
+  ) {
+    metadata.refs.insert(
+      MAIN_BRANCH.to_string(),
+      SnapshotReference {
+        type_: "branch".to_string(),
+        snapshot_id: id,
+        max_ref_age_ms: None,
+        max_snapshot_age_ms: None,
+        min_snapshots_to_keep: None,
+      },
+    );
+  }
+  Ok(metadata)
+}
+
+// Tables go under the `location` property of their namespace if it is set, otherwise under the
+// warehouse.
+pub fn default_location(
+  namespace_level: &[NamespaceIdent],
+  namespace: &Namespace,
+  table_name: &str,
+  warehouse: &Warehouse,
+) -> String {
+  match namespace
+    .properties
+    .get("location")
+    .and_then(|x| x.as_str())
+  {
+    Some(location) => format!("{}/{}", location.trim_end_matches('/'), table_name),
+    None => warehouse.default_location(namespace_level, table_name),
+  }
+}
+
+// Metadata files are written under the location of the table, or under `write.metadata.path`.
+fn check_locations(metadata: &TableMetadata, warehouse: &Warehouse) -> Result<()> {
+  if let Some(location) = &metadata.location {
+    warehouse.check_location(location)?;
+  }
+  if let Some(path) = metadata.properties.get(METADATA_PATH_PROPERTY) {
+    warehouse.check_location(path)?;
+  }
+  Ok(())
+}
+
+// Write the current metadata of the table to a new metadata file and point the table at it. The
+// previous file is added to the metadata log. Tables stored outside of the local file system keep
+// their metadata in the catalog only.
+fn write_metadata_file(
+  table: &mut Table,
+  previous_updated_ms: Option<i64>,
+  files: &mut MetadataFiles,
+) -> Result<()> {
+  let metadata = &mut table.metadata;
+  let metadata_dir = match metadata.properties.get(METADATA_PATH_PROPERTY) {
+    Some(path) => path.clone(),
+    None => match &metadata.location {
+      Some(location) => format!("{}/metadata", location.trim_end_matches('/')),
+      None => {
+        table.metadata_location = None;
+        return Ok(());
+      }
+    },
+  };
+  let previous = table.metadata_location.take();
+  let location = match file_io::new_metadata_location(&metadata_dir, previous.as_deref()) {
+    Some(location) => location,
+    None => return Ok(()),
+  };
+
+  if let (Some(metadata_file), Some(timestamp_ms)) = (previous, previous_updated_ms) {
+    metadata.metadata_log.push(MetadataLogEntry {
+      metadata_file,
+      timestamp_ms,
+    });
+    let max_versions = metadata
+      .properties
+      .get(PREVIOUS_VERSIONS_MAX_PROPERTY)
+      .and_then(|x| x.parse::<usize>().ok())
+      .unwrap_or(DEFAULT_PREVIOUS_VERSIONS_MAX)
+      .max(1);
+    let len = metadata.metadata_log.len();
+    if len > max_versions {
+      metadata.metadata_log.drain(..len - max_versions);
+    }
+  }
+
+  files.write(&location, metadata)?;
+  table.metadata_location = Some(location);
+  Ok(())
+}
+
+const MAIN_BRANCH: &str = "main";
+const FORMAT_VERSION_PROPERTY: &str = "format-version";
+const DEFAULT_FORMAT_VERSION: i32 = 2;
+const METADATA_PATH_PROPERTY: &str = "write.metadata.path";
+const PREVIOUS_VERSIONS_MAX_PROPERTY: &str = "write.metadata.previous-versions-max";
+const DEFAULT_PREVIOUS_VERSIONS_MAX: usize = 100;
+
+fn remove_ref(metadata: &mut TableMetadata, name: &str) {
+  metadata.refs.remove(name);
+  if name == MAIN_BRANCH {
+    metadata.current_snapshot_id = None;
   }
 }
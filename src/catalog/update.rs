@@ -0,0 +1,29 @@
+use crate::{
+  common::result::{ErrorType, Location, Result},
+  err,
+};
+
+// Helpers shared by the updates of table and view metadata, `location` tells which one failed.
+
+pub fn invalid_update<T>(location: Location, message: String) -> Result<T> {
+  err!(ErrorType::BadRequest, location, message)
+}
+
+// Resolve the -1 shorthand of the updates to the id added last in this commit.
+pub fn resolve_last_added(
+  location: Location,
+  id: i32,
+  last_added: Option<i32>,
+  what: &str,
+) -> Result<i32> {
+  if id != -1 {
+    return Ok(id);
+  }
+  match last_added {
+    Some(id) => Ok(id),
+    None => invalid_update(
+      location,
+      format!("Cannot set last added {}: no {} has been added", what, what),
+    ),
+  }
+}
@@ -0,0 +1,483 @@
+use crate::{
+  catalog::{
+    file_io::{self, MetadataFiles, Warehouse},
+    namespace::{Namespace, NamespaceIdent},
+    schema,
+    table::default_location,
+    update::{invalid_update, resolve_last_added},
+  },
+  common::result::{ErrorType, Location, Result},
+  db::{store::is_conflict, DBConnection, DBRead, Key, Transaction},
+  err,
+  server::routes::common::*,
+  server::routes::request::*,
+  util::time,
+};
+use rocket::serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+// Views are stored like tables: under `Key::View`, listed in the `views` of their namespace and
+// with their metadata written to a new metadata file on every change.
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct View {
+  pub name: String,
+  pub metadata: ViewMetadata,
+  // location of the metadata file of the current metadata, if any
+  #[serde(default)]
+  pub metadata_location: Option<String>,
+}
+
+impl View {
+  pub fn exists(conn: &impl DBRead, namespace: &[NamespaceIdent], view_name: &str) -> Result<bool> {
+    conn.exists(&Key::view(namespace, view_name))
+  }
+
+  pub fn get(
+    conn: &impl DBRead,
+    namespace: &[NamespaceIdent],
+    view_name: &str,
+  ) -> Result<Option<View>> {
+    conn.get::<View>(&Key::view(namespace, view_name))
+  }
+
+  pub fn list(conn: &impl DBRead, namespace: &[NamespaceIdent]) -> Result<Option<Vec<String>>> {
+    let key = Key::namespace(namespace);
+    Ok(
+      conn
+        .get::<Namespace>(&key)?
+        .map(|namespace_instance| namespace_instance.views),
+    )
+  }
+
+  pub fn create(
+    conn: &DBConnection,
+    namespace: &[NamespaceIdent],
+    request: &CreateViewRequest,
+    warehouse: &Warehouse,
+  ) -> Result<View> {
+    let mut files = MetadataFiles::default();
+    let result = conn.transact(|txn| {
+      files.new_attempt();
+      View::create_in(txn, namespace, request, warehouse, &mut files)
+    });
+    files.finish(result)
+  }
+
+  fn create_in(
+    txn: &mut Transaction,
+    namespace: &[NamespaceIdent],
+    request: &CreateViewRequest,
+    warehouse: &Warehouse,
+    files: &mut MetadataFiles,
+  ) -> Result<View> {
+    if request.name.is_empty() {
+      return err!(
+        ErrorType::BadRequest,
+        Location::View,
+        "View name cannot be empty".to_string()
+      );
+    }
+    let namespace_key = Key::namespace(namespace);
+    let mut namespace_instance = match txn.get::<Namespace>(&namespace_key)? {
+      Some(namespace_instance) => namespace_instance,
+      None => {
+        return err!(
+          ErrorType::NotFound,
+          Location::Namespace,
+          format!("Namespace {} not found", namespace_key)
+        )
+      }
+    };
+    Namespace::check_name_free(txn, namespace, &request.name)?;
+    schema::validate_schema(&request.schema)?;
+
+    let location = match &request.location {
+      Some(location) => location.clone(),
+      None => default_location(namespace, &namespace_instance, &request.name, warehouse),
+    };
+    warehouse.check_location(&location)?;
+    let mut metadata = ViewMetadata {
+      view_uuid: uuid::Uuid::new_v4().to_string(),
+      format_version: FORMAT_VERSION,
+      location,
+      current_version_id: 0,
+      versions: vec![],
+      version_log: vec![],
+      schemas: vec![],
+      properties: request.properties.clone(),
+    };
+    // the first version always uses the schema of the request, whatever its schema id says
+    let schema_id = add_schema(&mut metadata, request.schema.clone());
+    let mut version = request.view_version.clone();
+    version.schema_id = schema_id;
+    let version_id = add_version(&mut metadata, version, Some(schema_id))?;
+    set_current_version(&mut metadata, version_id)?;
+
+    let mut view = View {
+      name: request.name.clone(),
+      metadata,
+      metadata_location: None,
+    };
+    write_metadata_file(&mut view, files)?;
+    txn.put(&Key::view(namespace, &view.name), &view)?;
+    namespace_instance.views.push(view.name.clone());
+    txn.put(&namespace_key, &namespace_instance)?;
+    Ok(view)
+  }
+
+  // Check the requirements against the current metadata and apply all the updates. Like table
+  // commits, a view changed by another commit after this one read it fails with a CommitFailed.
+  pub fn commit(
+    conn: &DBConnection,
+    namespace: &[NamespaceIdent],
+    view_name: String,
+    requirements: &[ViewRequirement],
+    updates: Vec<ViewUpdate>,
+    warehouse: &Warehouse,
+  ) -> Result<View> {
+    let mut files = MetadataFiles::default();
+    let result = conn.try_transact(|txn| {
+      View::commit_in(
+        txn,
+        namespace,
+        &view_name,
+        requirements,
+        updates,
+        warehouse,
+        &mut files,
+      )
+    });
+    match files.finish(result) {
+      Err(e) if is_conflict(&e) => err!(
+        ErrorType::CommitFailed,
+        Location::View,
+        format!(
+          "View {} was changed by a concurrent commit",
+          Key::view(namespace, &view_name)
+        )
+      ),
+      result => result,
+    }
+  }
+
+  fn commit_in(
+    txn: &mut Transaction,
+    namespace: &[NamespaceIdent],
+    view_name: &str,
+    requirements: &[ViewRequirement],
+    updates: Vec<ViewUpdate>,
+    warehouse: &Warehouse,
+    files: &mut MetadataFiles,
+  ) -> Result<View> {
+    let key = Key::view(namespace, view_name);
+    let mut view = match txn.get::<View>(&key)? {
+      Some(view) => view,
+      None => {
+        return err!(
+          ErrorType::NotFound,
+          Location::View,
+          format!("View {} not found", key)
+        )
+      }
+    };
+    for requirement in requirements {
+      match requirement {
+        ViewRequirement::AssertViewUUID(r) => {
+          if r.uuid != view.metadata.view_uuid {
+            return err!(
+              ErrorType::CommitFailed,
+              Location::View,
+              format!(
+                "Requirement failed: view uuid changed: expected {} != {}",
+                r.uuid, view.metadata.view_uuid
+              )
+            );
+          }
+        }
+      }
+    }
+    apply_updates(&mut view.metadata, updates)?;
+    warehouse.check_location(&view.metadata.location)?;
+    write_metadata_file(&mut view, files)?;
+    txn.put(&key, &view)?;
+    Ok(view)
+  }
+
+  pub fn delete(
+    conn: &DBConnection,
+    namespace: &[NamespaceIdent],
+    view_name: String,
+  ) -> Result<()> {
+    conn.transact(|txn| View::delete_in(txn, namespace, &view_name))
+  }
+
+  fn delete_in(txn: &mut Transaction, namespace: &[NamespaceIdent], view_name: &str) -> Result<()> {
+    let key = Key::view(namespace, view_name);
+    if !txn.exists(&key)? {
+      return err!(
+        ErrorType::NotFound,
+        Location::View,
+        format!("View {} not found", key)
+      );
+    }
+    let namespace_key = Key::namespace(namespace);
+    if let Some(mut namespace_instance) = txn.get::<Namespace>(&namespace_key)? {
+      namespace_instance.views.retain(|name| name != view_name);
+      txn.put(&namespace_key, &namespace_instance)?;
+    }
+    txn.delete(&key);
+    Ok(())
+  }
+
+  // Move a view to a new name, possibly in another namespace, like `Table::rename`.
+  pub fn rename(
+    conn: &DBConnection,
+    source_namespace: &[NamespaceIdent],
+    source_name: String,
+    destination_namespace: &[NamespaceIdent],
+    destination_name: String,
+  ) -> Result<()> {
+    conn.transact(|txn| {
+      View::rename_in(
+        txn,
+        source_namespace,
+        &source_name,
+        destination_namespace,
+        &destination_name,
+      )
+    })
+  }
+
+  fn rename_in(
+    txn: &mut Transaction,
+    source_namespace: &[NamespaceIdent],
+    source_name: &str,
+    destination_namespace: &[NamespaceIdent],
+    destination_name: &str,
+  ) -> Result<()> {
+    Namespace::move_entry(
+      txn,
+      Location::View,
+      source_namespace,
+      source_name,
+      destination_namespace,
+      destination_name,
+      |view: &mut View| view.name = destination_name.to_string(),
+    )
+  }
+}
+
+// Apply the updates in order, following the rules of the Iceberg `ViewMetadata` builder.
+pub fn apply_updates(metadata: &mut ViewMetadata, updates: Vec<ViewUpdate>) -> Result<()> {
+  let mut last_added_schema_id = None;
+  let mut last_added_version_id = None;
+
+  for update in updates {
+    match update {
+      ViewUpdate::AssignUUID(u) => {
+        if u.uuid != metadata.view_uuid {
+          return invalid_update(
+            Location::View,
+            "Cannot reassign the uuid of a view".to_string(),
+          );
+        }
+      }
+      ViewUpdate::UpgradeFormatVersion(u) => {
+        if u.format_version > FORMAT_VERSION {
+          return invalid_update(
+            Location::View,
+            format!(
+              "Cannot upgrade view to unsupported format version v{}",
+              u.format_version
+            ),
+          );
+        }
+        if u.format_version < metadata.format_version {
+          return invalid_update(
+            Location::View,
+            format!(
+              "Cannot downgrade v{} view to v{}",
+              metadata.format_version, u.format_version
+            ),
+          );
+        }
+        metadata.format_version = u.format_version;
+      }
+      ViewUpdate::AddSchema(u) => {
+        schema::validate_schema(&u.schema)?;
+        last_added_schema_id = Some(add_schema(metadata, u.schema));
+      }
+      ViewUpdate::SetLocation(u) => metadata.location = u.location,
+      ViewUpdate::SetProperties(u) => metadata.properties.extend(u.updates),
+      ViewUpdate::RemoveProperties(u) => {
+        for key in u.removals {
+          metadata.properties.remove(&key);
+        }
+      }
+      ViewUpdate::AddViewVersion(u) => {
+        last_added_version_id = Some(add_version(metadata, u.view_version, last_added_schema_id)?);
+      }
+      ViewUpdate::SetCurrentViewVersion(u) => {
+        let version_id = resolve_last_added(
+          Location::View,
+          u.view_version_id,
+          last_added_version_id,
+          "version",
+        )?;
+        set_current_version(metadata, version_id)?;
+      }
+    }
+  }
+  expire_versions(metadata);
+  Ok(())
+}
+
+// Add a schema to the view and return its id. A schema equal to one the view has reuses its id.
+fn add_schema(metadata: &mut ViewMetadata, mut schema: Schema) -> i32 {
+  let mut schema_id = 0;
+  for existing in metadata.schemas.iter() {
+    if existing.struct_type == schema.struct_type
+      && existing.identifier_field_ids == schema.identifier_field_ids
+    {
+      return existing.schema_id;
+    }
+    schema_id = schema_id.max(existing.schema_id + 1);
+  }
+  schema.schema_id = schema_id;
+  metadata.schemas.push(schema);
+  schema_id
+}
+
+// Versions are equal when they would show the same query, whatever their id and timestamp.
+fn same_version(a: &ViewVersion, b: &ViewVersion) -> bool {
+  a.schema_id == b.schema_id
+    && a.summary == b.summary
+    && a.representations == b.representations
+    && a.default_catalog == b.default_catalog
+    && a.default_namespace == b.default_namespace
+}
+
+// Add a version to the view and return its id. A version equal to one the view has reuses its id.
+fn add_version(
+  metadata: &mut ViewMetadata,
+  mut version: ViewVersion,
+  last_added_schema_id: Option<i32>,
+) -> Result<i32> {
+  version.schema_id = resolve_last_added(
+    Location::View,
+    version.schema_id,
+    last_added_schema_id,
+    "schema",
+  )?;
+  if !metadata
+    .schemas
+    .iter()
+    .any(|schema| schema.schema_id == version.schema_id)
+  {
+    return invalid_update(
+      Location::View,
+      format!(
+        "Cannot add version with unknown schema: {}",
+        version.schema_id
+      ),
+    );
+  }
+  if version.representations.is_empty() {
+    return invalid_update(
+      Location::View,
+      "Cannot add a version without representations".to_string(),
+    );
+  }
+  // a version has at most one query per dialect
+  let mut dialects = HashSet::new();
+  for representation in version.representations.iter() {
+    match representation {
+      ViewRepresentation::Sql(sql) => {
+        if !dialects.insert(sql.dialect.to_lowercase()) {
+          return invalid_update(
+            Location::View,
+            format!("Cannot add multiple queries for dialect {}", sql.dialect),
+          );
+        }
+      }
+    }
+  }
+
+  let mut version_id = 1;
+  for existing in metadata.versions.iter() {
+    if same_version(existing, &version) {
+      return Ok(existing.version_id);
+    }
+    version_id = version_id.max(existing.version_id + 1);
+  }
+  version.version_id = version_id;
+  metadata.versions.push(version);
+  Ok(version_id)
+}
+
+fn set_current_version(metadata: &mut ViewMetadata, version_id: i32) -> Result<()> {
+  if !metadata.versions.iter().any(|v| v.version_id == version_id) {
+    return invalid_update(
+      Location::View,
+      format!(
+        "Cannot set current version to unknown version: {}",
+        version_id
+      ),
+    );
+  }
+  if metadata.current_version_id == version_id && !metadata.version_log.is_empty() {
+    return Ok(());
+  }
+  metadata.current_version_id = version_id;
+  metadata.version_log.push(ViewHistoryEntry {
+    version_id,
+    timestamp_ms: time::now_millis(),
+  });
+  Ok(())
+}
+
+// Only the newest versions are kept, along with the current one. The version log keeps the
+// entries after the last one that refers to an expired version.
+fn expire_versions(metadata: &mut ViewMetadata) {
+  let max_versions = metadata
+    .properties
+    .get(VERSION_HISTORY_PROPERTY)
+    .and_then(|x| x.parse::<usize>().ok())
+    .unwrap_or(DEFAULT_VERSION_HISTORY)
+    .max(1);
+  if metadata.versions.len() <= max_versions {
+    return;
+  }
+  let mut ids: Vec<i32> = metadata.versions.iter().map(|v| v.version_id).collect();
+  ids.sort_unstable_by(|a, b| b.cmp(a));
+  let mut kept: HashSet<i32> = ids.into_iter().take(max_versions).collect();
+  kept.insert(metadata.current_version_id);
+  metadata.versions.retain(|v| kept.contains(&v.version_id));
+  if let Some(last) = metadata
+    .version_log
+    .iter()
+    .rposition(|entry| !kept.contains(&entry.version_id))
+  {
+    metadata.version_log.drain(..=last);
+  }
+}
+
+// Write the metadata of the view to a new metadata file, in the `metadata` directory of the view
+// like the ones of tables. Views outside of the local file system keep their metadata in the
+// catalog only.
+fn write_metadata_file(view: &mut View, files: &mut MetadataFiles) -> Result<()> {
+  let metadata_dir = format!("{}/metadata", view.metadata.location.trim_end_matches('/'));
+  let previous = view.metadata_location.take();
+  let location = match file_io::new_metadata_location(&metadata_dir, previous.as_deref()) {
+    Some(location) => location,
+    None => return Ok(()),
+  };
+  files.write(&location, &view.metadata)?;
+  view.metadata_location = Some(location);
+  Ok(())
+}
+
+const FORMAT_VERSION: i32 = 1;
+const VERSION_HISTORY_PROPERTY: &str = "version.history.num-entries";
+const DEFAULT_VERSION_HISTORY: usize = 10;
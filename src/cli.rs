@@ -2,12 +2,49 @@ use std::path::PathBuf;
 
 use clap::Parser;
 
+use crate::db::Backend;
+
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
   /// Sets the root of database
   #[arg(short, long, value_name = "db_root", default_value = "./database")]
   pub db_root: Option<PathBuf>,
+
+  /// Sets the root of the warehouse, where tables without an explicit location are stored
+  #[arg(short, long, value_name = "warehouse", default_value = "./warehouse")]
+  pub warehouse: Option<PathBuf>,
+
+  /// Sets the storage engine of the catalog databases
+  #[arg(short, long, value_enum, default_value_t = Backend::Pickledb)]
+  pub backend: Backend,
+
+  /// Sets the name of the default catalog in the tables of the sqlite backend, warehouses use
+  /// their own name
+  #[arg(long, value_name = "catalog_name", default_value = "default")]
+  pub catalog_name: String,
+
+  /// Sets the JSON file with the catalog properties returned by `/v1/config`
+  #[arg(short, long, value_name = "config")]
+  pub config: Option<PathBuf>,
+
+  /// Adds a default catalog property, overriding the config file
+  #[arg(long = "default", value_name = "key=value", value_parser = parse_property)]
+  pub defaults: Vec<(String, String)>,
+
+  /// Adds an override catalog property, overriding the config file
+  #[arg(long = "override", value_name = "key=value", value_parser = parse_property)]
+  pub overrides: Vec<(String, String)>,
+}
+
+fn parse_property(property: &str) -> Result<(String, String), String> {
+  match property.split_once('=') {
+    Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+    _ => Err(format!(
+      "invalid property `{}`, expected key=value",
+      property
+    )),
+  }
 }
 
 pub fn parse() -> Cli {
@@ -15,6 +15,8 @@ impl ErrorType {
       ErrorType::NotFound => Status::NotFound,
       ErrorType::ServiceUnavailable => Status::ServiceUnavailable,
       ErrorType::AlreadyExists => Status::Conflict,
+      ErrorType::CommitFailed => Status::Conflict,
+      ErrorType::NamespaceNotEmpty => Status::Conflict,
       ErrorType::Unprocessable => Status::UnprocessableEntity,
       ErrorType::InternalError => Status::InternalServerError,
     }
@@ -52,7 +54,7 @@ impl<'r> Responder<'r, 'static> for Empty {
 #[macro_export]
 macro_rules! err {
   ($error_type:expr, $location:expr, $message:expr) => {
-    Err(crate::common::result::Error {
+    Err($crate::common::result::Error {
       error_type: $error_type,
       location: $location,
       message: $message,
@@ -72,6 +74,6 @@ macro_rules! ok_json {
 #[macro_export]
 macro_rules! ok_empty {
   () => {
-    Ok(crate::common::result::Empty {})
+    Ok($crate::common::result::Empty {})
   };
 }
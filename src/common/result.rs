@@ -13,6 +13,8 @@ pub enum ErrorType {
   NotFound,
   ServiceUnavailable,
   AlreadyExists,
+  CommitFailed,
+  NamespaceNotEmpty,
   Unprocessable,
   InternalError,
 }
@@ -24,6 +26,7 @@ pub enum Location {
   Namespace,
   Request,
   Table,
+  View,
 }
 
 #[derive(Builder, Serialize)]
@@ -58,6 +61,7 @@ impl fmt::Display for Location {
       Location::Table => write!(f, "table"),
       Location::DB => write!(f, "DB"),
       Location::Request => write!(f, "request"),
+      Location::View => write!(f, "view"),
     }
   }
 }
@@ -71,6 +75,10 @@ impl fmt::Display for Error {
         write!(f, "[{}] Unavailable: {}", self.location, self.message)
       }
       ErrorType::AlreadyExists => write!(f, "[{}] Already Exists: {}", self.location, self.message),
+      ErrorType::CommitFailed => write!(f, "[{}] Commit Failed: {}", self.location, self.message),
+      ErrorType::NamespaceNotEmpty => {
+        write!(f, "[{}] Not Empty: {}", self.location, self.message)
+      }
       ErrorType::Unprocessable => write!(f, "[{}] Unprocessable: {}", self.location, self.message),
       ErrorType::InternalError => write!(f, "[{}] Internal Error: {}", self.location, self.message),
     }
@@ -0,0 +1,153 @@
+use std::fmt;
+
+/// Key of an entry in the catalog db.
+///
+/// Keys are encoded as `<kind>/<level 1>/.../<level n>[/<name>]`. Every part is escaped so it
+/// never contains a `/`, which keeps the encoding unambiguous whatever the names are.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Key {
+  Namespace(Vec<String>),
+  Table(Vec<String>, String),
+  View(Vec<String>, String),
+  // status of the purge of the files of a dropped table
+  Purge(Vec<String>, String),
+  // a dropped table or namespace kept until its retention expires, by drop id
+  Dropped(String),
+  // marks a one-time migration of the catalog as done, by migration name
+  Migration(String),
+}
+
+pub const NAMESPACE: &str = "namespace";
+pub const TABLE: &str = "table";
+pub const VIEW: &str = "view";
+pub const PURGE: &str = "purge";
+pub const DROPPED: &str = "dropped";
+pub const MIGRATION: &str = "migration";
+
+fn escape(part: &str) -> String {
+  part.replace('%', "%25").replace('/', "%2F")
+}
+
+fn unescape(part: &str) -> String {
+  part.replace("%2F", "/").replace("%25", "%")
+}
+
+impl Key {
+  pub fn namespace(level: &[String]) -> Key {
+    Key::Namespace(level.to_vec())
+  }
+
+  pub fn table(namespace: &[String], name: &str) -> Key {
+    Key::Table(namespace.to_vec(), name.to_string())
+  }
+
+  pub fn view(namespace: &[String], name: &str) -> Key {
+    Key::View(namespace.to_vec(), name.to_string())
+  }
+
+  pub fn purge(namespace: &[String], table_name: &str) -> Key {
+    Key::Purge(namespace.to_vec(), table_name.to_string())
+  }
+
+  // Prefix of the encoded keys of a kind in a namespace and its descendants, e.g. `table/a/` for
+  // the tables under `a`. Scanning it skips the rest of the catalog.
+  pub fn prefix(kind: &str, level: &[String]) -> String {
+    let mut prefix = kind.to_string();
+    for part in level {
+      prefix.push('/');
+      prefix.push_str(&escape(part));
+    }
+    prefix.push('/');
+    prefix
+  }
+
+  pub fn encode(&self) -> String {
+    let (kind, level, name) = match self {
+      Key::Namespace(level) => (NAMESPACE, level.as_slice(), None),
+      Key::Table(level, name) => (TABLE, level.as_slice(), Some(name)),
+      Key::View(level, name) => (VIEW, level.as_slice(), Some(name)),
+      Key::Purge(level, name) => (PURGE, level.as_slice(), Some(name)),
+      Key::Dropped(id) => (DROPPED, [].as_slice(), Some(id)),
+      Key::Migration(name) => (MIGRATION, [].as_slice(), Some(name)),
+    };
+    let mut key = kind.to_string();
+    for part in level.iter().chain(name) {
+      key.push('/');
+      key.push_str(&escape(part));
+    }
+    key
+  }
+
+  pub fn decode(key: &str) -> Option<Key> {
+    let mut parts = key.split('/');
+    let kind = parts.next()?;
+    let mut level: Vec<String> = parts.map(unescape).collect();
+    match kind {
+      NAMESPACE => Some(Key::Namespace(level)),
+      TABLE => level.pop().map(|name| Key::Table(level, name)),
+      VIEW => level.pop().map(|name| Key::View(level, name)),
+      PURGE => level.pop().map(|name| Key::Purge(level, name)),
+      DROPPED if level.len() == 1 => level.pop().map(Key::Dropped),
+      MIGRATION if level.len() == 1 => level.pop().map(Key::Migration),
+      _ => None,
+    }
+  }
+}
+
+// Human readable form used in error messages, e.g. `a.b.t`.
+impl fmt::Display for Key {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Key::Namespace(level) if level.is_empty() => write!(f, "root"),
+      Key::Namespace(level) => write!(f, "{}", level.join(".")),
+      Key::Dropped(id) | Key::Migration(id) => write!(f, "{}", id),
+      Key::Table(level, name) | Key::View(level, name) | Key::Purge(level, name)
+        if level.is_empty() =>
+      {
+        write!(f, "{}", name)
+      }
+      Key::Table(level, name) | Key::View(level, name) | Key::Purge(level, name) => {
+        write!(f, "{}.{}", level.join("."), name)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_key_round_trip() {
+    let level = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    let keys = [
+      Key::namespace(&[]),
+      Key::namespace(&level(&["a", "b"])),
+      Key::namespace(&level(&["a/b", "100%"])),
+      Key::table(&[], "t"),
+      Key::table(&level(&["a"]), "x_y"),
+      Key::table(&level(&["a_x"]), "y"),
+      Key::table(&level(&["a", "b%2F"]), "c/d"),
+      Key::View(level(&["a"]), "v".to_string()),
+      Key::Purge(level(&["a"]), "t".to_string()),
+      Key::Dropped("5f0c".to_string()),
+      Key::Migration("table-uuids".to_string()),
+    ];
+    for key in keys.iter() {
+      assert_eq!(Key::decode(&key.encode()).as_ref(), Some(key));
+    }
+    // names that used to collide get distinct keys
+    assert_ne!(keys[4].encode(), keys[5].encode());
+    assert_eq!(keys[1].encode(), "namespace/a/b");
+    assert_eq!(keys[6].encode(), "table/a/b%252F/c%2Fd");
+    assert_eq!(Key::decode("root::a"), None);
+    assert_eq!(Key::decode("table"), None);
+    assert_eq!(Key::prefix(TABLE, &level(&["a/b"])), "table/a%2Fb/");
+    assert!(keys[6]
+      .encode()
+      .starts_with(&Key::prefix(TABLE, &level(&["a"]))));
+    assert!(!keys[5]
+      .encode()
+      .starts_with(&Key::prefix(TABLE, &level(&["a"]))));
+  }
+}
@@ -1,46 +1,51 @@
 use crate::{
-  catalog::namespace::Namespace,
+  catalog::{dropped::Dropped, namespace::Namespace, purge::Purge, table::Table},
   common::result::{Error, ErrorType, Location, Result},
   err,
 };
-use pickledb::PickleDb;
+use pickledb::{PickleDb, PickleDbDumpPolicy, SerializationMethod};
 
-use rocket::serde::Serialize;
-use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
-use std::{fs, path::PathBuf};
+use rocket::serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+pub mod key;
+mod pickle_store;
+mod redb_store;
+mod sqlite_store;
+pub mod store;
+pub use key::Key;
+pub use store::Backend;
+use store::{is_conflict, CatalogStore, Write};
 
 pub struct DB {
-  conn: RwLock<DBConnection>, // simple rw lock
+  // connections are shared by all requests, concurrent writers are checked when they commit
+  conn: Arc<DBConnection>,
+  // metrics reports are kept in their own file so they do not bloat the catalog
+  metrics_conn: Arc<DBConnection>,
 }
 
 impl DB {
-  pub fn get_read_conn(&self) -> Result<RwLockReadGuard<DBConnection>> {
-    let read_guard = self.conn.read();
-    if read_guard.is_err() {
-      return err!(
-        ErrorType::InternalError,
-        Location::DB,
-        "Failed to get read connection".to_owned()
-      );
-    }
-
-    Ok(read_guard.unwrap())
+  pub fn conn(&self) -> &DBConnection {
+    &self.conn
   }
 
-  pub fn get_write_conn(&self) -> Result<RwLockWriteGuard<DBConnection>> {
-    let write_guard = self.conn.write();
-    if write_guard.is_err() {
-      return err!(
-        ErrorType::InternalError,
-        Location::DB,
-        "Failed to get write connection".to_owned()
-      );
-    }
+  // The catalog connection for work that outlives a request, such as purges.
+  pub fn shared_conn(&self) -> Arc<DBConnection> {
+    self.conn.clone()
+  }
 
-    Ok(write_guard.unwrap())
+  pub fn metrics_conn(&self) -> &DBConnection {
+    &self.metrics_conn
   }
 
-  pub fn new(root_dir: PathBuf) -> Result<DB> {
+  pub fn new(root_dir: PathBuf, backend: Backend, catalog_name: &str) -> Result<DB> {
     println!("starting db in {:?}", root_dir);
     if !std::path::Path::new(&root_dir).exists() {
       let res = fs::create_dir(&root_dir);
@@ -53,64 +58,609 @@ impl DB {
       }
     }
 
-    let mut conn = DBConnection::new(&root_dir)?;
-    Namespace::init(&mut conn)?;
-    Ok(DB {
-      conn: RwLock::new(conn),
-    })
+    if backend == Backend::Pickledb {
+      let catalog_path = root_dir.join("catalog.namespace");
+      let metrics_path = root_dir.join("catalog.metrics");
+      // metrics are moved first, the legacy keys are found from the catalog
+      let legacy = legacy_keys(&catalog_path);
+      migrate_legacy_keys(&metrics_path, &legacy)?;
+      migrate_legacy_keys(&catalog_path, &legacy)?;
+    }
+
+    let conn = Arc::new(DBConnection(backend.open(
+      &root_dir,
+      "namespace",
+      catalog_name,
+    )?));
+    Namespace::init(&conn)?;
+    Table::migrate_uuids(&conn)?;
+    // purges interrupted by a restart start over
+    Purge::resume(&conn)?;
+    let metrics_conn = Arc::new(DBConnection(backend.open(
+      &root_dir,
+      "metrics",
+      catalog_name,
+    )?));
+    Dropped::start_sweeper(Arc::downgrade(&conn), Arc::downgrade(&metrics_conn));
+    Ok(DB { conn, metrics_conn })
   }
 }
 
-pub struct DBConnection(PickleDb);
+// transactions that keep conflicting with other writers give up after this many attempts
+const MAX_TRANSACTION_ATTEMPTS: usize = 16;
+
+/// Reads of the catalog db, either directly or inside a transaction.
+/// A key that is missing reads as `Ok(None)`, a failing store or a value that does not
+/// deserialize as an error.
+pub trait DBRead {
+  fn get<V: DeserializeOwned>(&self, key: &Key) -> Result<Option<V>>;
+
+  fn exists(&self, key: &Key) -> Result<bool> {
+    Ok(self.get::<Value>(key)?.is_some())
+  }
+}
+
+pub struct DBConnection(Box<dyn CatalogStore>);
+
+fn from_value<V: DeserializeOwned>(key: &Key, value: Option<Value>) -> Result<Option<V>> {
+  match value.map(serde_json::from_value).transpose() {
+    Ok(value) => Ok(value),
+    Err(e) => err!(
+      ErrorType::InternalError,
+      Location::DB,
+      format!("Failed to get key: {}, error: {}", key, e)
+    ),
+  }
+}
+
+fn read(conn: &DBConnection, key: &Key) -> Result<Option<Value>> {
+  conn.0.get(&key.encode()).map_err(|e| Error {
+    message: format!("Failed to get key: {}, error: {}", key, e.message),
+    ..e
+  })
+}
+
+impl DBRead for DBConnection {
+  fn get<V: DeserializeOwned>(&self, key: &Key) -> Result<Option<V>> {
+    from_value(key, read(self, key)?)
+  }
+}
+
+fn to_value<T: Serialize>(key: &Key, value: &T) -> Result<Value> {
+  match serde_json::to_value(value) {
+    Ok(value) => Ok(value),
+    Err(e) => err!(
+      ErrorType::InternalError,
+      Location::DB,
+      format!("Failed to put key: {}, error: {}", key, e)
+    ),
+  }
+}
 
 impl DBConnection {
-  pub fn exists(&self, key: &str) -> bool {
-    self.0.exists(key)
-  }
-
-  pub fn get<V: for<'de> rocket::serde::Deserialize<'de>>(&self, key: &str) -> Option<V> {
-    self.0.get(key)
-  }
-
-  pub fn put<T: Serialize>(&mut self, key: &str, value: &T) -> Result<()> {
-    match self.0.set(key, &value) {
-      Ok(_) => Ok(()),
-      Err(e) => Err(Error {
-        error_type: ErrorType::InternalError,
-        location: Location::DB,
-        message: format!("Failed to put key: {}, error: {}", key, e),
-      }),
-    }
-  }
-
-  pub fn delete(&mut self, key: &str) -> Result<()> {
-    match self.0.rem(key) {
-      Ok(_) => Ok(()),
-      Err(e) => Err(Error {
-        error_type: ErrorType::InternalError,
-        location: Location::DB,
-        message: format!("Failed to delete key: {}, error: {}", key, e),
-      }),
-    }
-  }
-
-  fn new(root_dir: &PathBuf) -> Result<DBConnection> {
-    // Load the database from disk, if no database exists, create a new one.
-    let db_path = root_dir.join("catalog.namespace");
-    match PickleDb::load(
-      &db_path,
-      pickledb::PickleDbDumpPolicy::AutoDump,
-      pickledb::SerializationMethod::Json,
-    ) {
-      Ok(conn) => Ok(DBConnection(conn)),
-      Err(_) => {
-        let conn = PickleDb::new(
-          &db_path,
-          pickledb::PickleDbDumpPolicy::AutoDump,
-          pickledb::SerializationMethod::Json,
-        );
-        Ok(DBConnection(conn))
+  pub fn keys(&self) -> Vec<Key> {
+    self.scan("")
+  }
+
+  // Keys whose encoding starts with `prefix`, see `Key::prefix`.
+  pub fn scan(&self, prefix: &str) -> Vec<Key> {
+    self
+      .0
+      .scan(prefix)
+      .unwrap_or_default()
+      .iter()
+      .filter_map(|key| Key::decode(key))
+      .collect()
+  }
+
+  // Write a single key whatever its current value is.
+  pub fn put<T: Serialize>(&self, key: &Key, value: &T) -> Result<()> {
+    self
+      .0
+      .put(&key.encode(), to_value(key, value)?)
+      .map_err(|e| Error {
+        message: format!("Failed to put key: {}, error: {}", key, e.message),
+        ..e
+      })
+  }
+
+  pub fn delete(&self, key: &Key) -> Result<()> {
+    self.0.delete(&key.encode()).map_err(|e| Error {
+      message: format!("Failed to delete key: {}, error: {}", key, e.message),
+      ..e
+    })
+  }
+
+  // Run `operation` in a transaction and commit it. When another writer changed one of the keys
+  // the operation read before it could commit, the operation runs again on the new values.
+  pub fn transact<T>(&self, mut operation: impl FnMut(&mut Transaction) -> Result<T>) -> Result<T> {
+    let mut attempt = 1;
+    loop {
+      match self.try_transact(&mut operation) {
+        Err(e) if is_conflict(&e) && attempt < MAX_TRANSACTION_ATTEMPTS => attempt += 1,
+        result => return result,
       }
     }
   }
+
+  // Run `operation` in a transaction and commit it once. When another writer changed one of the
+  // keys the operation read, nothing is written and the conflict is returned (see `is_conflict`).
+  pub fn try_transact<T>(
+    &self,
+    operation: impl FnOnce(&mut Transaction) -> Result<T>,
+  ) -> Result<T> {
+    let mut txn = Transaction {
+      conn: self,
+      writes: vec![],
+      pending: HashMap::new(),
+      reads: RefCell::new(HashMap::new()),
+    };
+    let result = operation(&mut txn)?;
+    txn.commit()?;
+    Ok(result)
+  }
+}
+
+/// Changes to several keys that become durable together, or not at all. Reads see the changes
+/// made earlier in the same transaction, and the keys read must not have changed when the
+/// transaction commits. Only `DBConnection::transact` and `try_transact` commit transactions.
+pub struct Transaction<'c> {
+  conn: &'c DBConnection,
+  writes: Vec<Write>,
+  pending: HashMap<Key, Option<Value>>,
+  // the value of every key the first time it was read from the db
+  reads: RefCell<HashMap<Key, Option<Value>>>,
+}
+
+impl DBRead for Transaction<'_> {
+  fn get<V: DeserializeOwned>(&self, key: &Key) -> Result<Option<V>> {
+    let value = match self.pending.get(key) {
+      Some(value) => value.clone(),
+      None => {
+        let mut reads = self.reads.borrow_mut();
+        match reads.get(key) {
+          Some(value) => value.clone(),
+          None => {
+            let value = read(self.conn, key)?;
+            reads.insert(key.clone(), value.clone());
+            value
+          }
+        }
+      }
+    };
+    from_value(key, value)
+  }
+}
+
+impl Transaction<'_> {
+  pub fn put<T: Serialize>(&mut self, key: &Key, value: &T) -> Result<()> {
+    let value = to_value(key, value)?;
+    self.writes.push(Write::Put(key.encode(), value.clone()));
+    self.pending.insert(key.clone(), Some(value));
+    Ok(())
+  }
+
+  pub fn delete(&mut self, key: &Key) {
+    self.writes.push(Write::Delete(key.encode()));
+    self.pending.insert(key.clone(), None);
+  }
+
+  fn commit(self) -> Result<()> {
+    // a transaction that only reads saw a consistent state, there is nothing to check
+    if self.writes.is_empty() {
+      return Ok(());
+    }
+    let mut writes: Vec<Write> = self
+      .reads
+      .into_inner()
+      .into_iter()
+      .map(|(key, value)| Write::Check(key.encode(), value))
+      .collect();
+    writes.extend(self.writes);
+    self.conn.0.transaction(writes).map_err(|e| Error {
+      message: format!("Failed to commit transaction, error: {}", e.message),
+      ..e
+    })
+  }
+}
+
+// Before `Key`, namespaces were stored under `root::a::b` and tables under
+// `<namespace key>_<table name>`, which is ambiguous when names contain `_` or `::`.
+fn legacy_namespace(key: &str) -> Option<Vec<String>> {
+  if key == "root" {
+    return Some(vec![]);
+  }
+  let level = key.strip_prefix("root::")?;
+  Some(level.split("::").map(|x| x.to_string()).collect())
+}
+
+// Map the legacy keys of a catalog file to the new keys. The stored values tell namespaces and
+// tables apart, and tables know their own name which resolves the ambiguity of the old keys.
+fn legacy_keys(db_path: &Path) -> HashMap<String, Key> {
+  let mut keys = HashMap::new();
+  let db = match PickleDb::load(
+    db_path,
+    PickleDbDumpPolicy::NeverDump,
+    SerializationMethod::Json,
+  ) {
+    Ok(db) => db,
+    Err(_) => return keys,
+  };
+  for key in db.get_all() {
+    if !key.starts_with("root") {
+      continue;
+    }
+    let value: Value = match db.get(&key) {
+      Some(value) => value,
+      None => continue,
+    };
+    if value.get("child").is_some() {
+      if let Some(level) = legacy_namespace(&key) {
+        keys.insert(key, Key::Namespace(level));
+      }
+      continue;
+    }
+    let name = match value.get("name").and_then(|x| x.as_str()) {
+      Some(name) => name,
+      None => continue,
+    };
+    let namespace = key
+      .strip_suffix(name)
+      .and_then(|x| x.strip_suffix('_'))
+      .and_then(legacy_namespace);
+    if let Some(level) = namespace {
+      keys.insert(key.clone(), Key::Table(level, name.to_string()));
+    }
+  }
+  keys
+}
+
+// Rename the legacy keys of a db file. All the changes are written at once.
+fn migrate_legacy_keys(db_path: &Path, keys: &HashMap<String, Key>) -> Result<()> {
+  let mut db = match PickleDb::load(
+    db_path,
+    PickleDbDumpPolicy::DumpUponRequest,
+    SerializationMethod::Json,
+  ) {
+    Ok(db) => db,
+    Err(_) => return Ok(()),
+  };
+  let legacy: Vec<String> = db
+    .get_all()
+    .into_iter()
+    .filter(|key| keys.contains_key(key))
+    .collect();
+  if legacy.is_empty() {
+    return Ok(());
+  }
+  for key in legacy {
+    let value: Value = db.get(&key).unwrap_or(Value::Null);
+    let result = db
+      .set(&keys[&key].encode(), &value)
+      .and_then(|_| db.rem(&key).map(|_| ()));
+    if let Err(e) = result {
+      return err!(
+        ErrorType::InternalError,
+        Location::DB,
+        format!("Failed to migrate key {}: {}", key, e)
+      );
+    }
+  }
+  match db.dump() {
+    Ok(_) => Ok(()),
+    Err(e) => err!(
+      ErrorType::InternalError,
+      Location::DB,
+      format!("Failed to write the migrated db {:?}: {}", db_path, e)
+    ),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::catalog::file_io::{self, Warehouse};
+  use crate::catalog::view::View;
+  use crate::server::routes::common::TableMetadataGenerator;
+  use crate::server::routes::request::TableUpdate;
+  use crate::server::routes::table::test::mock_create_table_request;
+  use crate::server::routes::view::test::mock_create_view_request;
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::sync::Arc;
+
+  // Passes the first `budget` transactions to the store and fails all the others, like a process
+  // that died: only the transactions committed before the crash are on disk.
+  struct CrashingStore {
+    store: Box<dyn CatalogStore>,
+    budget: Arc<AtomicUsize>,
+  }
+
+  impl CatalogStore for CrashingStore {
+    fn get(&self, key: &str) -> Result<Option<Value>> {
+      self.store.get(key)
+    }
+
+    fn scan(&self, prefix: &str) -> Result<Vec<String>> {
+      self.store.scan(prefix)
+    }
+
+    fn transaction(&self, writes: Vec<Write>) -> Result<()> {
+      let left = self
+        .budget
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |x| x.checked_sub(1));
+      if left.is_err() {
+        return err!(ErrorType::InternalError, Location::DB, "crashed".to_owned());
+      }
+      self.store.transaction(writes)
+    }
+  }
+
+  // Lets another writer put `race` right before the next transaction commits.
+  struct RacingStore {
+    store: Box<dyn CatalogStore>,
+    race: std::sync::Mutex<Option<(Key, Value)>>,
+  }
+
+  impl CatalogStore for RacingStore {
+    fn get(&self, key: &str) -> Result<Option<Value>> {
+      self.store.get(key)
+    }
+
+    fn scan(&self, prefix: &str) -> Result<Vec<String>> {
+      self.store.scan(prefix)
+    }
+
+    fn transaction(&self, writes: Vec<Write>) -> Result<()> {
+      if let Some((key, value)) = self.race.lock().unwrap().take() {
+        self.store.put(&key.encode(), value)?;
+      }
+      self.store.transaction(writes)
+    }
+  }
+
+  fn level(x: &[&str]) -> Vec<String> {
+    x.iter().map(|x| x.to_string()).collect()
+  }
+
+  fn run_operations(conn: &DBConnection, warehouse: &Warehouse) -> Result<()> {
+    let generator = TableMetadataGenerator::new();
+    let (a, b) = (level(&["a"]), level(&["a", "b"]));
+    Namespace::create(conn, &a, None)?;
+    Namespace::create(conn, &b, None)?;
+    for (namespace, name) in [(&a, "t"), (&b, "u")] {
+      let request = mock_create_table_request(name);
+      Table::create(conn, namespace, &request, (&generator).into(), warehouse)?;
+    }
+    Table::rename(conn, &b, "u".to_string(), &a, "v".to_string())?;
+    Table::delete(conn, &a, "v".to_string(), false, 0, warehouse)?;
+    View::create(conn, &b, &mock_create_view_request("w"), warehouse)?;
+    View::rename(conn, &b, "w".to_string(), &a, "w".to_string())?;
+    View::delete(conn, &a, "w".to_string())?;
+    Table::delete(conn, &a, "t".to_string(), false, 0, warehouse)?;
+    Namespace::delete(conn, &b, 0)
+  }
+
+  // Every namespace is linked from its parent and links exactly its namespaces and tables.
+  fn check_consistent(conn: &DBConnection) {
+    let keys = conn.keys();
+    for key in keys.iter() {
+      match key {
+        Key::Namespace(level) => {
+          let namespace: Namespace = conn.get(key).unwrap().unwrap();
+          for child in namespace.child.iter() {
+            let mut child_level = level.clone();
+            child_level.push(child.clone());
+            assert!(keys.contains(&Key::namespace(&child_level)), "{}", key);
+          }
+          for table in namespace.tables.iter() {
+            assert!(keys.contains(&Key::table(level, table)), "{}", key);
+          }
+          for view in namespace.views.iter() {
+            assert!(keys.contains(&Key::view(level, view)), "{}", key);
+          }
+          if let Some((name, parent)) = level.split_last() {
+            let parent: Namespace = conn.get(&Key::namespace(parent)).unwrap().unwrap();
+            assert!(parent.child.contains(name), "{}", key);
+          }
+        }
+        Key::Table(level, name) => {
+          let namespace: Namespace = conn.get(&Key::namespace(level)).unwrap().unwrap();
+          assert!(namespace.tables.contains(name), "{}", key);
+        }
+        Key::View(level, name) => {
+          let namespace: Namespace = conn.get(&Key::namespace(level)).unwrap().unwrap();
+          assert!(namespace.views.contains(name), "{}", key);
+        }
+        // purges and dropped tables and namespaces are not linked from namespaces
+        Key::Purge(..) | Key::Dropped(_) | Key::Migration(_) => (),
+      }
+    }
+  }
+
+  #[test]
+  fn test_crash_between_transactions() {
+    for backend in [Backend::Pickledb, Backend::Redb, Backend::Sqlite] {
+      for budget in 0.. {
+        let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+        let root = temp_dir.path().join("db");
+        let warehouse = Warehouse::new(temp_dir.path().join("warehouse")).unwrap();
+        drop(DB::new(root.clone(), backend, "test").unwrap());
+
+        let conn = DBConnection(Box::new(CrashingStore {
+          store: backend.open(&root, "namespace", "test").unwrap(),
+          budget: Arc::new(AtomicUsize::new(budget)),
+        }));
+        let result = run_operations(&conn, &warehouse);
+        drop(conn);
+
+        // whatever was committed before the crash is consistent
+        let conn = DBConnection(backend.open(&root, "namespace", "test").unwrap());
+        check_consistent(&conn);
+        if result.is_ok() {
+          let (dropped, keys): (Vec<_>, Vec<_>) = conn
+            .keys()
+            .into_iter()
+            .partition(|key| matches!(key, Key::Dropped(_)));
+          assert_eq!(
+            keys,
+            vec![
+              Key::Migration("table-uuids".to_string()),
+              Key::namespace(&[]),
+              Key::namespace(&level(&["a"]))
+            ]
+          );
+          // the two dropped tables and the dropped namespace
+          assert_eq!(dropped.len(), 3);
+          assert_eq!(conn.scan(&Key::prefix(key::DROPPED, &[])), dropped);
+          assert_eq!(conn.scan(&Key::prefix(key::TABLE, &[])), vec![]);
+          break;
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_get_invalid_value() {
+    for backend in [Backend::Pickledb, Backend::Redb, Backend::Sqlite] {
+      let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+      let db = DB::new(temp_dir.path().join("db"), backend, "test").unwrap();
+      let key = Key::Dropped("x".to_string());
+      db.conn().put(&key, &"not a namespace").unwrap();
+
+      // a value that cannot be read is an error, not a missing key
+      let error = db.conn().get::<Namespace>(&key).err().unwrap();
+      assert!(matches!(error.error_type, ErrorType::InternalError));
+      let error = db
+        .conn()
+        .try_transact(|txn| txn.get::<Namespace>(&key))
+        .err()
+        .unwrap();
+      assert!(matches!(error.error_type, ErrorType::InternalError));
+      assert!(db.conn().exists(&key).unwrap());
+      assert!(db
+        .conn()
+        .get::<Namespace>(&Key::namespace(&level(&["b"])))
+        .unwrap()
+        .is_none());
+    }
+  }
+
+  #[test]
+  fn test_concurrent_commits() {
+    for backend in [Backend::Pickledb, Backend::Redb, Backend::Sqlite] {
+      let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+      let warehouse = Warehouse::new(temp_dir.path().join("warehouse")).unwrap();
+      let db = DB::new(temp_dir.path().join("db"), backend, "test").unwrap();
+      let generator = TableMetadataGenerator::new();
+      let a = level(&["a"]);
+      Namespace::create(db.conn(), &a, None).unwrap();
+      let request = mock_create_table_request("shared");
+      Table::create(db.conn(), &a, &request, (&generator).into(), &warehouse).unwrap();
+
+      // every thread adds a table to the namespace and a property to the shared table, the
+      // writers that lose a race retry instead of overwriting the winners
+      std::thread::scope(|scope| {
+        for i in 0..8 {
+          let (db, a, generator, warehouse) = (&db, &a, &generator, &warehouse);
+          scope.spawn(move || {
+            let request = mock_create_table_request(&format!("t{}", i));
+            Table::create(db.conn(), a, &request, generator.into(), warehouse).unwrap();
+            let update: TableUpdate = serde_json::from_value(serde_json::json!({
+              "action": "set-properties",
+              "updates": { format!("k{}", i): "v" },
+            }))
+            .unwrap();
+            // table commits are not retried by the catalog, clients retry them like engines do
+            loop {
+              let name = "shared".to_string();
+              let updates = vec![update.clone()];
+              match Table::commit(
+                db.conn(),
+                a,
+                name,
+                &[],
+                updates,
+                generator.into(),
+                warehouse,
+              ) {
+                Ok(_) => break,
+                Err(e) => assert!(matches!(e.error_type, ErrorType::CommitFailed)),
+              }
+            }
+          });
+        }
+      });
+
+      let table = Table::get(db.conn(), &a, "shared".to_string())
+        .unwrap()
+        .unwrap();
+      assert_eq!(table.metadata.properties.len(), 8, "{:?}", backend);
+      let namespace: Namespace = db.conn().get(&Key::namespace(&a)).unwrap().unwrap();
+      assert_eq!(namespace.tables.len(), 9, "{:?}", backend);
+      check_consistent(db.conn());
+    }
+  }
+
+  #[test]
+  fn test_table_commit_compare_and_swap() {
+    for backend in [Backend::Pickledb, Backend::Redb, Backend::Sqlite] {
+      let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+      let root = temp_dir.path().join("db");
+      let warehouse = Warehouse::new(temp_dir.path().join("warehouse")).unwrap();
+      let generator = TableMetadataGenerator::new();
+      let a = level(&["a"]);
+      let db = DB::new(root.clone(), backend, "test").unwrap();
+      Namespace::create(db.conn(), &a, None).unwrap();
+      let request = mock_create_table_request("t");
+      let mut table =
+        Table::create(db.conn(), &a, &request, (&generator).into(), &warehouse).unwrap();
+      drop(db);
+
+      // another writer commits between the read and the write of the table
+      table
+        .metadata
+        .properties
+        .insert("writer".to_string(), "other".to_string());
+      let conn = DBConnection(Box::new(RacingStore {
+        store: backend.open(&root, "namespace", "test").unwrap(),
+        race: std::sync::Mutex::new(Some((
+          Key::table(&a, "t"),
+          serde_json::to_value(&table).unwrap(),
+        ))),
+      }));
+      let commit = || {
+        let update: TableUpdate = serde_json::from_value(serde_json::json!({
+          "action": "set-properties",
+          "updates": { "writer": "this" },
+        }))
+        .unwrap();
+        let name = "t".to_string();
+        Table::commit(
+          &conn,
+          &a,
+          name,
+          &[],
+          vec![update],
+          (&generator).into(),
+          &warehouse,
+        )
+      };
+      let error = commit().err().expect("the commit should fail");
+      assert!(matches!(error.error_type, ErrorType::CommitFailed));
+      assert!(matches!(error.location, Location::Table));
+      let stored = Table::get(&conn, &a, "t".to_string()).unwrap().unwrap();
+      assert_eq!(stored.metadata.properties["writer"], "other");
+      // the metadata file of the failed commit is deleted
+      let location = stored.metadata.location.clone().unwrap();
+      let metadata_dir = file_io::local_path(&location).unwrap().join("metadata");
+      let metadata_files = || std::fs::read_dir(&metadata_dir).unwrap().count();
+      assert_eq!(metadata_files(), 1);
+
+      // a retry starts from the new version
+      commit().unwrap();
+      let stored = Table::get(&conn, &a, "t".to_string()).unwrap().unwrap();
+      assert_eq!(stored.metadata.properties["writer"], "this");
+      assert_eq!(metadata_files(), 2);
+    }
+  }
 }
@@ -0,0 +1,130 @@
+use crate::common::result::{ErrorType, Location, Result};
+use crate::db::store::{conflict, CatalogStore, Write};
+use crate::err;
+use pickledb::{PickleDb, PickleDbDumpPolicy, SerializationMethod};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Store backed by a pickledb JSON file. The whole file is written once per transaction, and
+/// transactions run one at a time.
+pub struct PickleStore(RwLock<PickleDb>);
+
+impl PickleStore {
+  pub fn open(db_path: &Path) -> Result<PickleStore> {
+    // Load the database from disk, if no database exists, create a new one.
+    match PickleDb::load(
+      db_path,
+      PickleDbDumpPolicy::DumpUponRequest,
+      SerializationMethod::Json,
+    ) {
+      Ok(db) => Ok(PickleStore(RwLock::new(db))),
+      Err(_) => {
+        let mut db = PickleDb::new(
+          db_path,
+          PickleDbDumpPolicy::DumpUponRequest,
+          SerializationMethod::Json,
+        );
+        match db.dump() {
+          Ok(_) => Ok(PickleStore(RwLock::new(db))),
+          Err(e) => err!(
+            ErrorType::InternalError,
+            Location::DB,
+            format!("Failed to create db {:?}: {}", db_path, e)
+          ),
+        }
+      }
+    }
+  }
+
+  fn read(&self) -> Result<RwLockReadGuard<'_, PickleDb>> {
+    match self.0.read() {
+      Ok(db) => Ok(db),
+      Err(_) => err!(
+        ErrorType::InternalError,
+        Location::DB,
+        "Failed to lock the db for reading".to_owned()
+      ),
+    }
+  }
+
+  fn write(&self) -> Result<RwLockWriteGuard<'_, PickleDb>> {
+    match self.0.write() {
+      Ok(db) => Ok(db),
+      Err(_) => err!(
+        ErrorType::InternalError,
+        Location::DB,
+        "Failed to lock the db for writing".to_owned()
+      ),
+    }
+  }
+}
+
+// Put back the values a failed transaction overwrote, the file was not written yet.
+fn rollback(db: &mut PickleDb, previous: Vec<(String, Option<Value>)>) {
+  for (key, value) in previous.into_iter().rev() {
+    let _ = match value {
+      Some(value) => db.set(&key, &value),
+      None => db.rem(&key).map(|_| ()),
+    };
+  }
+}
+
+impl CatalogStore for PickleStore {
+  fn get(&self, key: &str) -> Result<Option<Value>> {
+    Ok(self.read()?.get(key))
+  }
+
+  fn scan(&self, prefix: &str) -> Result<Vec<String>> {
+    let mut keys: Vec<String> = self
+      .read()?
+      .get_all()
+      .into_iter()
+      .filter(|key| key.starts_with(prefix))
+      .collect();
+    keys.sort();
+    Ok(keys)
+  }
+
+  fn transaction(&self, writes: Vec<Write>) -> Result<()> {
+    let mut db = self.write()?;
+    for write in writes.iter() {
+      if let Write::Check(key, expected) = write {
+        if db.get::<Value>(key) != *expected {
+          return conflict(key);
+        }
+      }
+    }
+    let mut previous = Vec::with_capacity(writes.len());
+    for write in writes {
+      let result = match write {
+        Write::Put(key, value) => {
+          previous.push((key.clone(), db.get::<Value>(&key)));
+          db.set(&key, &value).map_err(|e| (key, e))
+        }
+        Write::Delete(key) => {
+          previous.push((key.clone(), db.get::<Value>(&key)));
+          db.rem(&key).map(|_| ()).map_err(|e| (key, e))
+        }
+        Write::Check(..) => Ok(()),
+      };
+      if let Err((key, e)) = result {
+        rollback(&mut db, previous);
+        return err!(
+          ErrorType::InternalError,
+          Location::DB,
+          format!("Failed to write key: {}, error: {}", key, e)
+        );
+      }
+    }
+    if let Err(e) = db.dump() {
+      rollback(&mut db, previous);
+      return err!(
+        ErrorType::InternalError,
+        Location::DB,
+        format!("Failed to write the db, error: {}", e)
+      );
+    }
+    Ok(())
+  }
+}
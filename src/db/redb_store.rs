@@ -0,0 +1,92 @@
+use crate::common::result::{ErrorType, Location, Result};
+use crate::db::store::{conflict, CatalogStore, Write};
+use crate::err;
+use redb::{Database, ReadableTable, TableDefinition};
+use serde_json::Value;
+use std::path::Path;
+
+const ENTRIES: TableDefinition<&str, &str> = TableDefinition::new("entries");
+
+/// Store backed by a redb file. Every transaction is a redb write transaction, only the pages of
+/// the changed keys are written. Reads never wait for writes.
+pub struct RedbStore(Database);
+
+fn db_error<T>(e: impl Into<redb::Error>) -> Result<T> {
+  err!(
+    ErrorType::InternalError,
+    Location::DB,
+    format!("redb error: {}", e.into())
+  )
+}
+
+impl RedbStore {
+  pub fn open(db_path: &Path) -> Result<RedbStore> {
+    let db = Database::create(db_path).or_else(db_error)?;
+    // create the table up front so reads never see it missing
+    let txn = db.begin_write().or_else(db_error)?;
+    txn.open_table(ENTRIES).or_else(db_error)?;
+    txn.commit().or_else(db_error)?;
+    Ok(RedbStore(db))
+  }
+}
+
+impl CatalogStore for RedbStore {
+  fn get(&self, key: &str) -> Result<Option<Value>> {
+    let txn = self.0.begin_read().or_else(db_error)?;
+    let table = txn.open_table(ENTRIES).or_else(db_error)?;
+    let value = match table.get(key).or_else(db_error)? {
+      Some(value) => value,
+      None => return Ok(None),
+    };
+    match serde_json::from_str(value.value()) {
+      Ok(value) => Ok(Some(value)),
+      Err(e) => err!(
+        ErrorType::InternalError,
+        Location::DB,
+        format!("Invalid value of key {}: {}", key, e)
+      ),
+    }
+  }
+
+  fn scan(&self, prefix: &str) -> Result<Vec<String>> {
+    let txn = self.0.begin_read().or_else(db_error)?;
+    let table = txn.open_table(ENTRIES).or_else(db_error)?;
+    let mut keys = vec![];
+    for entry in table.range(prefix..).or_else(db_error)? {
+      let (key, _) = entry.or_else(db_error)?;
+      if !key.value().starts_with(prefix) {
+        break;
+      }
+      keys.push(key.value().to_string());
+    }
+    Ok(keys)
+  }
+
+  fn transaction(&self, writes: Vec<Write>) -> Result<()> {
+    let txn = self.0.begin_write().or_else(db_error)?;
+    {
+      let mut table = txn.open_table(ENTRIES).or_else(db_error)?;
+      for write in writes {
+        match write {
+          Write::Put(key, value) => {
+            table
+              .insert(key.as_str(), value.to_string().as_str())
+              .or_else(db_error)?;
+          }
+          Write::Delete(key) => {
+            table.remove(key.as_str()).or_else(db_error)?;
+          }
+          Write::Check(key, expected) => {
+            let current = table.get(key.as_str()).or_else(db_error)?;
+            let current: Option<Value> = current.and_then(|x| serde_json::from_str(x.value()).ok());
+            if current != expected {
+              return conflict(&key);
+            }
+          }
+        }
+      }
+    }
+    // an error before the commit drops the transaction, which aborts it
+    txn.commit().or_else(db_error)
+  }
+}
@@ -0,0 +1,601 @@
+use crate::catalog::file_io;
+use crate::common::result::{ErrorType, Location, Result};
+use crate::db::store::{conflict, CatalogStore, Write};
+use crate::db::{key, Key};
+use crate::err;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::sync::Mutex;
+
+// Tables of the Iceberg JDBC catalog (schema V1), created the same way the JDBC catalog does.
+const CREATE_TABLES: &str = "CREATE TABLE IF NOT EXISTS iceberg_tables (
+  catalog_name VARCHAR(255) NOT NULL,
+  table_namespace VARCHAR(255) NOT NULL,
+  table_name VARCHAR(255) NOT NULL,
+  metadata_location VARCHAR(1000),
+  previous_metadata_location VARCHAR(1000),
+  iceberg_type VARCHAR(5),
+  PRIMARY KEY (catalog_name, table_namespace, table_name)
+)";
+const CREATE_NAMESPACE_PROPERTIES: &str =
+  "CREATE TABLE IF NOT EXISTS iceberg_namespace_properties (
+  catalog_name VARCHAR(255) NOT NULL,
+  namespace VARCHAR(255) NOT NULL,
+  property_key VARCHAR(255),
+  property_value VARCHAR(1000),
+  PRIMARY KEY (catalog_name, namespace, property_key)
+)";
+// Everything the JDBC schema has no room for: the full table and namespace documents, the root
+// namespace and the entries that are neither namespaces nor tables.
+const CREATE_ENTRIES: &str = "CREATE TABLE IF NOT EXISTS catalog2_entries (
+  catalog_name VARCHAR(255) NOT NULL,
+  entry_key TEXT NOT NULL,
+  entry_value TEXT NOT NULL,
+  PRIMARY KEY (catalog_name, entry_key)
+)";
+
+// The JDBC catalog marks a namespace without properties with this property.
+const NAMESPACE_EXISTS_PROPERTY: &str = "exists";
+const TABLE_TYPE: &str = "TABLE";
+
+/// Store backed by a SQLite file.
+///
+/// With `jdbc`, namespaces and tables are kept in the tables of the Iceberg JDBC catalog so that
+/// JDBC based tools can share the catalog: the JDBC tables are the source of truth for which
+/// namespaces and tables exist and for the current metadata location of every table. Table
+/// commits are a compare-and-swap on `metadata_location`, like the JDBC catalog does.
+pub struct SqliteStore {
+  conn: Mutex<Connection>,
+  catalog_name: String,
+  jdbc: bool,
+}
+
+fn sql_error<T>(e: rusqlite::Error) -> Result<T> {
+  err!(
+    ErrorType::InternalError,
+    Location::DB,
+    format!("SQLite error: {}", e)
+  )
+}
+
+// JDBC catalogs store a namespace as its levels joined by `.`, levels cannot contain one.
+fn namespace_name(level: &[String]) -> Result<String> {
+  if level.iter().any(|x| x.contains('.')) {
+    return err!(
+      ErrorType::BadRequest,
+      Location::Namespace,
+      format!(
+        "Namespace {} cannot be stored in a JDBC catalog, its levels contain a `.`",
+        level.join(".")
+      )
+    );
+  }
+  Ok(level.join("."))
+}
+
+fn namespace_level(name: &str) -> Vec<String> {
+  if name.is_empty() {
+    return vec![];
+  }
+  name.split('.').map(|x| x.to_string()).collect()
+}
+
+impl SqliteStore {
+  pub fn open(db_path: &Path, catalog_name: &str, jdbc: bool) -> Result<SqliteStore> {
+    let conn = Connection::open(db_path).or_else(sql_error)?;
+    for statement in [CREATE_TABLES, CREATE_NAMESPACE_PROPERTIES, CREATE_ENTRIES] {
+      conn.execute(statement, []).or_else(sql_error)?;
+    }
+    Ok(SqliteStore {
+      conn: Mutex::new(conn),
+      catalog_name: catalog_name.to_string(),
+      jdbc,
+    })
+  }
+
+  fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
+    match self.conn.lock() {
+      Ok(conn) => Ok(conn),
+      Err(_) => err!(
+        ErrorType::InternalError,
+        Location::DB,
+        "Failed to lock the SQLite connection".to_owned()
+      ),
+    }
+  }
+
+  fn get_entry(&self, conn: &Connection, key: &str) -> Result<Option<Value>> {
+    let value: Option<String> = conn
+      .query_row(
+        "SELECT entry_value FROM catalog2_entries WHERE catalog_name = ?1 AND entry_key = ?2",
+        params![self.catalog_name, key],
+        |row| row.get(0),
+      )
+      .optional()
+      .or_else(sql_error)?;
+    match value.map(|x| serde_json::from_str(&x)) {
+      None => Ok(None),
+      Some(Ok(value)) => Ok(Some(value)),
+      Some(Err(e)) => err!(
+        ErrorType::InternalError,
+        Location::DB,
+        format!("Invalid value of key {}: {}", key, e)
+      ),
+    }
+  }
+
+  fn put_entry(&self, conn: &Connection, key: &str, value: &Value) -> Result<()> {
+    conn
+      .execute(
+        "INSERT OR REPLACE INTO catalog2_entries (catalog_name, entry_key, entry_value)
+         VALUES (?1, ?2, ?3)",
+        params![self.catalog_name, key, value.to_string()],
+      )
+      .map(|_| ())
+      .or_else(sql_error)
+  }
+
+  fn delete_entry(&self, conn: &Connection, key: &str) -> Result<()> {
+    conn
+      .execute(
+        "DELETE FROM catalog2_entries WHERE catalog_name = ?1 AND entry_key = ?2",
+        params![self.catalog_name, key],
+      )
+      .map(|_| ())
+      .or_else(sql_error)
+  }
+
+  // The metadata location of a table row, `None` when the table does not exist.
+  fn table_location(
+    &self,
+    conn: &Connection,
+    namespace: &str,
+    name: &str,
+  ) -> Result<Option<Option<String>>> {
+    conn
+      .query_row(
+        "SELECT metadata_location FROM iceberg_tables
+         WHERE catalog_name = ?1 AND table_namespace = ?2 AND table_name = ?3
+         AND (iceberg_type = ?4 OR iceberg_type IS NULL)",
+        params![self.catalog_name, namespace, name, TABLE_TYPE],
+        |row| row.get(0),
+      )
+      .optional()
+      .or_else(sql_error)
+  }
+
+  // The namespace `under` and its descendants, all the namespaces when `under` is the root.
+  fn namespace_names(&self, conn: &Connection, under: &str) -> Result<BTreeSet<String>> {
+    let mut statement = conn
+      .prepare(
+        "SELECT namespace FROM iceberg_namespace_properties
+         WHERE catalog_name = ?1
+           AND (?2 = '' OR namespace = ?2 OR substr(namespace, 1, length(?2) + 1) = ?2 || '.')
+         UNION SELECT table_namespace FROM iceberg_tables
+         WHERE catalog_name = ?1
+           AND (?2 = '' OR table_namespace = ?2
+                OR substr(table_namespace, 1, length(?2) + 1) = ?2 || '.')",
+      )
+      .or_else(sql_error)?;
+    let rows = statement
+      .query_map(params![self.catalog_name, under], |row| row.get(0))
+      .or_else(sql_error)?;
+    rows.collect::<rusqlite::Result<_>>().or_else(sql_error)
+  }
+
+  // The tables of `namespace`, of every namespace when it is None.
+  fn table_names(
+    &self,
+    conn: &Connection,
+    namespace: Option<&str>,
+  ) -> Result<Vec<(String, String)>> {
+    let mut statement = conn
+      .prepare(
+        "SELECT table_namespace, table_name FROM iceberg_tables
+         WHERE catalog_name = ?1 AND (iceberg_type = ?2 OR iceberg_type IS NULL)
+           AND (?3 IS NULL OR table_namespace = ?3)
+         ORDER BY table_namespace, table_name",
+      )
+      .or_else(sql_error)?;
+    let rows = statement
+      .query_map(params![self.catalog_name, TABLE_TYPE, namespace], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+      })
+      .or_else(sql_error)?;
+    rows.collect::<rusqlite::Result<_>>().or_else(sql_error)
+  }
+
+  fn get_table(
+    &self,
+    conn: &Connection,
+    key: &str,
+    namespace: &[String],
+    name: &str,
+  ) -> Result<Option<Value>> {
+    let location = match self.table_location(conn, &namespace_name(namespace)?, name)? {
+      Some(location) => location,
+      None => return Ok(None),
+    };
+    let entry = self.get_entry(conn, key)?;
+    let entry_location = entry
+      .as_ref()
+      .map(|x| x.get("metadata_location").and_then(|x| x.as_str()));
+    if entry.is_some() && entry_location == Some(location.as_deref()) {
+      return Ok(entry);
+    }
+    // the table was created or committed by another catalog, load its current metadata
+    let location = match location {
+      Some(location) => location,
+      None => return Ok(entry),
+    };
+    let metadata = file_io::read_metadata(&location)?;
+    Ok(Some(json!({
+      "name": name,
+      "metadata": metadata,
+      "metadata_location": location,
+    })))
+  }
+
+  fn put_table(
+    &self,
+    conn: &Connection,
+    key: &str,
+    namespace: &[String],
+    name: &str,
+    value: Value,
+  ) -> Result<()> {
+    let namespace = namespace_name(namespace)?;
+    let new_location = value.get("metadata_location").and_then(|x| x.as_str());
+    match self.table_location(conn, &namespace, name)? {
+      None => {
+        conn
+          .execute(
+            "INSERT INTO iceberg_tables (catalog_name, table_namespace, table_name,
+             metadata_location, previous_metadata_location, iceberg_type)
+             VALUES (?1, ?2, ?3, ?4, NULL, ?5)",
+            params![self.catalog_name, namespace, name, new_location, TABLE_TYPE],
+          )
+          .or_else(sql_error)?;
+      }
+      Some(location) if location.as_deref() == new_location => (),
+      Some(location) => {
+        // the location the commit was based on is the last one of the metadata log
+        let expected = value["metadata"]["metadata-log"]
+          .as_array()
+          .and_then(|x| x.last())
+          .and_then(|x| x["metadata-file"].as_str())
+          .map(|x| x.to_string())
+          .or_else(|| {
+            self
+              .get_entry(conn, key)
+              .ok()
+              .flatten()
+              .and_then(|x| x["metadata_location"].as_str().map(|x| x.to_string()))
+          })
+          .or(location);
+        let updated = conn
+          .execute(
+            "UPDATE iceberg_tables
+             SET metadata_location = ?1, previous_metadata_location = ?2
+             WHERE catalog_name = ?3 AND table_namespace = ?4 AND table_name = ?5
+             AND metadata_location IS ?2",
+            params![new_location, expected, self.catalog_name, namespace, name],
+          )
+          .or_else(sql_error)?;
+        if updated != 1 {
+          return err!(
+            ErrorType::CommitFailed,
+            Location::Table,
+            format!(
+              "Table {}.{} was changed concurrently, its metadata location is no longer {}",
+              namespace,
+              name,
+              expected.unwrap_or_default()
+            )
+          );
+        }
+      }
+    }
+    self.put_entry(conn, key, &value)
+  }
+
+  fn get_namespace(&self, conn: &Connection, key: &str, level: &[String]) -> Result<Option<Value>> {
+    let name = namespace_name(level)?;
+    let names = self.namespace_names(conn, &name)?;
+    let entry = self.get_entry(conn, key)?;
+    // the root namespace only lives in the entries
+    if !level.is_empty() && !names.contains(&name) {
+      return Ok(None);
+    }
+    let mut namespace = match entry {
+      Some(Value::Object(entry)) => entry,
+      _ if level.is_empty() => return Ok(None),
+      _ => Map::new(),
+    };
+
+    let mut child = BTreeSet::new();
+    for other in names.iter() {
+      let other = namespace_level(other);
+      if other.len() > level.len() && other.starts_with(level) {
+        child.insert(other[level.len()].clone());
+      }
+    }
+    namespace.insert("child".to_string(), json!(child));
+    let tables: Vec<String> = self
+      .table_names(conn, Some(&name))?
+      .into_iter()
+      .map(|(_, table)| table)
+      .collect();
+    namespace.insert("tables".to_string(), json!(tables));
+    if !level.is_empty() {
+      let mut statement = conn
+        .prepare(
+          "SELECT property_key, property_value FROM iceberg_namespace_properties
+           WHERE catalog_name = ?1 AND namespace = ?2 AND property_key != ?3",
+        )
+        .or_else(sql_error)?;
+      let rows = statement
+        .query_map(
+          params![self.catalog_name, name, NAMESPACE_EXISTS_PROPERTY],
+          |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)),
+        )
+        .or_else(sql_error)?;
+      let mut properties = Map::new();
+      for row in rows {
+        let (key, value) = row.or_else(sql_error)?;
+        properties.insert(key, json!(value));
+      }
+      namespace.insert("properties".to_string(), Value::Object(properties));
+    }
+    Ok(Some(Value::Object(namespace)))
+  }
+
+  fn put_namespace(
+    &self,
+    conn: &Connection,
+    key: &str,
+    level: &[String],
+    value: Value,
+  ) -> Result<()> {
+    let name = namespace_name(level)?;
+    if !level.is_empty() {
+      self.delete_namespace_properties(conn, &name)?;
+      let mut properties = vec![(NAMESPACE_EXISTS_PROPERTY.to_string(), "true".to_string())];
+      if let Some(Value::Object(map)) = value.get("properties") {
+        for (key, value) in map {
+          let value = match value {
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+          };
+          properties.push((key.clone(), value));
+        }
+      }
+      for (key, value) in properties {
+        conn
+          .execute(
+            "INSERT OR REPLACE INTO iceberg_namespace_properties
+             (catalog_name, namespace, property_key, property_value) VALUES (?1, ?2, ?3, ?4)",
+            params![self.catalog_name, name, key, value],
+          )
+          .or_else(sql_error)?;
+      }
+    }
+    self.put_entry(conn, key, &value)
+  }
+
+  fn delete_namespace_properties(&self, conn: &Connection, name: &str) -> Result<()> {
+    conn
+      .execute(
+        "DELETE FROM iceberg_namespace_properties WHERE catalog_name = ?1 AND namespace = ?2",
+        params![self.catalog_name, name],
+      )
+      .map(|_| ())
+      .or_else(sql_error)
+  }
+
+  fn apply(&self, conn: &Connection, write: Write) -> Result<()> {
+    match write {
+      Write::Put(key, value) => match self.decode(&key) {
+        Some(Key::Table(namespace, name)) => self.put_table(conn, &key, &namespace, &name, value),
+        Some(Key::Namespace(level)) => self.put_namespace(conn, &key, &level, value),
+        _ => self.put_entry(conn, &key, &value),
+      },
+      Write::Delete(key) => {
+        match self.decode(&key) {
+          Some(Key::Table(namespace, name)) => {
+            conn
+              .execute(
+                "DELETE FROM iceberg_tables
+                 WHERE catalog_name = ?1 AND table_namespace = ?2 AND table_name = ?3",
+                params![self.catalog_name, namespace_name(&namespace)?, name],
+              )
+              .or_else(sql_error)?;
+          }
+          Some(Key::Namespace(level)) if !level.is_empty() => {
+            self.delete_namespace_properties(conn, &namespace_name(&level)?)?;
+          }
+          _ => (),
+        }
+        self.delete_entry(conn, &key)
+      }
+      Write::Check(key, expected) => {
+        if self.read(conn, &key)? != expected {
+          return conflict(&key);
+        }
+        Ok(())
+      }
+    }
+  }
+
+  fn read(&self, conn: &Connection, key: &str) -> Result<Option<Value>> {
+    match self.decode(key) {
+      Some(Key::Table(namespace, name)) => self.get_table(conn, key, &namespace, &name),
+      Some(Key::Namespace(level)) => self.get_namespace(conn, key, &level),
+      _ => self.get_entry(conn, key),
+    }
+  }
+
+  // Keys kept in the JDBC tables, every other key is a plain entry.
+  fn decode(&self, key: &str) -> Option<Key> {
+    if !self.jdbc {
+      return None;
+    }
+    match Key::decode(key)? {
+      key @ (Key::Table(..) | Key::Namespace(_)) => Some(key),
+      _ => None,
+    }
+  }
+}
+
+impl CatalogStore for SqliteStore {
+  fn get(&self, key: &str) -> Result<Option<Value>> {
+    let conn = self.lock()?;
+    self.read(&conn, key)
+  }
+
+  fn scan(&self, prefix: &str) -> Result<Vec<String>> {
+    let conn = self.lock()?;
+    let mut keys = BTreeSet::new();
+    let mut statement = conn
+      .prepare(
+        "SELECT entry_key FROM catalog2_entries
+         WHERE catalog_name = ?1 AND substr(entry_key, 1, length(?2)) = ?2",
+      )
+      .or_else(sql_error)?;
+    let rows = statement
+      .query_map(params![self.catalog_name, prefix], |row| {
+        row.get::<_, String>(0)
+      })
+      .or_else(sql_error)?;
+    for key in rows {
+      let key = key.or_else(sql_error)?;
+      // namespaces and tables are listed from the JDBC tables, except the root namespace
+      match self.decode(&key) {
+        Some(Key::Namespace(level)) if level.is_empty() => keys.insert(key),
+        Some(_) => false,
+        None => keys.insert(key),
+      };
+    }
+    // the JDBC tables are only listed if the prefix can match namespace or table keys
+    let listed = |kind: &str| kind.starts_with(prefix) || prefix.starts_with(kind);
+    if self.jdbc && listed(key::NAMESPACE) {
+      for name in self.namespace_names(&conn, "")? {
+        keys.insert(Key::namespace(&namespace_level(&name)).encode());
+      }
+    }
+    if self.jdbc && listed(key::TABLE) {
+      for (namespace, name) in self.table_names(&conn, None)? {
+        keys.insert(Key::table(&namespace_level(&namespace), &name).encode());
+      }
+    }
+    Ok(keys.into_iter().filter(|x| x.starts_with(prefix)).collect())
+  }
+
+  fn transaction(&self, writes: Vec<Write>) -> Result<()> {
+    let mut conn = self.lock()?;
+    let txn = conn.transaction().or_else(sql_error)?;
+    for write in writes {
+      // dropping the transaction on error rolls it back
+      self.apply(&txn, write)?;
+    }
+    txn.commit().or_else(sql_error)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_metadata_location_compare_and_swap() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let path = temp_dir.path().join("catalog.sqlite");
+    let store = SqliteStore::open(&path, "test", true).unwrap();
+    let level = vec!["a".to_string()];
+    let key = Key::table(&level, "t").encode();
+    let table = |location: &str, previous: &[&str]| {
+      let log: Vec<Value> = previous
+        .iter()
+        .map(|x| json!({ "metadata-file": x, "timestamp-ms": 0 }))
+        .collect();
+      json!({
+        "name": "t",
+        "metadata": { "metadata-log": log },
+        "metadata_location": location,
+      })
+    };
+    store
+      .transaction(vec![
+        Write::Put(
+          Key::namespace(&level).encode(),
+          json!({ "properties": { "owner": "x" } }),
+        ),
+        Write::Put(key.clone(), table("v1", &[])),
+      ])
+      .unwrap();
+    store.put(&key, table("v2", &["v1"])).unwrap();
+
+    // a JDBC client reads the same rows
+    let conn = Connection::open(&path).unwrap();
+    let row: (String, String) = conn
+      .query_row(
+        "SELECT metadata_location, previous_metadata_location FROM iceberg_tables
+         WHERE catalog_name = 'test' AND table_namespace = 'a' AND table_name = 't'",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+      )
+      .unwrap();
+    assert_eq!(row, ("v2".to_string(), "v1".to_string()));
+    let owner: String = conn
+      .query_row(
+        "SELECT property_value FROM iceberg_namespace_properties
+         WHERE namespace = 'a' AND property_key = 'owner'",
+        [],
+        |row| row.get(0),
+      )
+      .unwrap();
+    assert_eq!(owner, "x");
+
+    // a commit based on a stale metadata location fails and changes nothing
+    let result = store.transaction(vec![
+      Write::Delete(Key::namespace(&level).encode()),
+      Write::Put(key.clone(), table("v3", &["v1"])),
+    ]);
+    assert!(matches!(
+      result.map_err(|e| e.error_type),
+      Err(ErrorType::CommitFailed)
+    ));
+    let namespace = store
+      .get(&Key::namespace(&level).encode())
+      .unwrap()
+      .unwrap();
+    assert_eq!(namespace["tables"], json!(["t"]));
+    assert_eq!(namespace["properties"], json!({ "owner": "x" }));
+    assert_eq!(store.get(&key).unwrap().unwrap()["metadata_location"], "v2");
+    assert_eq!(
+      store.scan("").unwrap(),
+      vec![Key::namespace(&level).encode(), key.clone()]
+    );
+
+    // namespaces whose names share a prefix are told apart
+    let sibling = vec!["ab".to_string()];
+    let child = vec!["a".to_string(), "b".to_string()];
+    let sibling_key = Key::table(&sibling, "t").encode();
+    store
+      .transaction(vec![
+        Write::Put(Key::namespace(&sibling).encode(), json!({})),
+        Write::Put(Key::namespace(&child).encode(), json!({})),
+        Write::Put(sibling_key.clone(), table("v1", &[])),
+      ])
+      .unwrap();
+    let namespace = store
+      .get(&Key::namespace(&level).encode())
+      .unwrap()
+      .unwrap();
+    assert_eq!(namespace["child"], json!(["b"]));
+    assert_eq!(namespace["tables"], json!(["t"]));
+    assert_eq!(store.scan("table/").unwrap(), vec![key, sibling_key]);
+  }
+}
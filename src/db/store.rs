@@ -0,0 +1,87 @@
+use crate::common::result::{Error, ErrorType, Location, Result};
+use crate::db::{pickle_store::PickleStore, redb_store::RedbStore, sqlite_store::SqliteStore};
+use crate::err;
+use serde_json::Value;
+use std::path::Path;
+
+/// Storage engine of a catalog db, selected with `--backend`.
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+  /// One JSON file per db, rewritten on every write
+  #[default]
+  Pickledb,
+  /// Embedded transactional key-value store, writes only touch the changed pages
+  Redb,
+  /// SQLite file using the schema of the Iceberg JDBC catalog, shared with JDBC clients
+  Sqlite,
+}
+
+/// A single change of a transaction.
+pub enum Write {
+  Put(String, Value),
+  Delete(String),
+  // the key must still have this value (`None` for a missing key) for the transaction to commit
+  Check(String, Option<Value>),
+}
+
+/// Key-value storage of a catalog db. Keys are encoded `Key`s and values are JSON documents.
+pub trait CatalogStore: Send + Sync {
+  fn get(&self, key: &str) -> Result<Option<Value>>;
+
+  // All the keys starting with `prefix`, in key order.
+  fn scan(&self, prefix: &str) -> Result<Vec<String>>;
+
+  // Apply all the writes, or none of them. Fails with `conflict` when a check does not hold.
+  // Transactions can run from several threads at once, stores serialize them as they need.
+  fn transaction(&self, writes: Vec<Write>) -> Result<()>;
+
+  fn put(&self, key: &str, value: Value) -> Result<()> {
+    self.transaction(vec![Write::Put(key.to_string(), value)])
+  }
+
+  fn delete(&self, key: &str) -> Result<()> {
+    self.transaction(vec![Write::Delete(key.to_string())])
+  }
+}
+
+impl Backend {
+  // Open the store called `name` in `root_dir`, creating it if needed. `catalog_name` is the name
+  // JDBC clients know the catalog by.
+  pub fn open(
+    &self,
+    root_dir: &Path,
+    name: &str,
+    catalog_name: &str,
+  ) -> Result<Box<dyn CatalogStore>> {
+    match self {
+      Backend::Pickledb => Ok(Box::new(PickleStore::open(
+        &root_dir.join(format!("catalog.{}", name)),
+      )?)),
+      Backend::Redb => Ok(Box::new(RedbStore::open(
+        &root_dir.join(format!("{}.redb", name)),
+      )?)),
+      // only the catalog itself goes in the JDBC tables, the metrics are plain entries
+      Backend::Sqlite => Ok(Box::new(SqliteStore::open(
+        &root_dir.join(format!("{}.sqlite", name)),
+        catalog_name,
+        name == "namespace",
+      )?)),
+    }
+  }
+}
+
+// Error of a transaction whose check failed because another transaction changed `key` first.
+pub fn conflict<T>(key: &str) -> Result<T> {
+  err!(
+    ErrorType::CommitFailed,
+    Location::DB,
+    format!("Key {} was changed by a concurrent transaction", key)
+  )
+}
+
+pub fn is_conflict(e: &Error) -> bool {
+  matches!(
+    (&e.error_type, &e.location),
+    (ErrorType::CommitFailed, Location::DB)
+  )
+}
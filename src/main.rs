@@ -8,25 +8,54 @@ mod util;
 #[macro_use]
 extern crate rocket;
 
+use catalog::file_io::Warehouse;
 use db::DB;
 
 use server::{
   catches,
-  routes::{common::TableMetadataAtomicIncr, *},
+  prefix::{self, Catalog, Catalogs},
+  routes::{common::TableMetadataGenerator, config::ServerConfig, *},
 };
 
 #[launch]
 pub fn rocket() -> _ {
   let cli = cli::parse();
-  let db = DB::new(cli.db_root.unwrap());
-  let table_metedata_generator = TableMetadataAtomicIncr::new();
+  let db_root = cli.db_root.unwrap();
+  let warehouse_root = cli.warehouse.unwrap();
+  let db = DB::new(db_root.clone(), cli.backend, &cli.catalog_name);
+  let table_metedata_generator = TableMetadataGenerator::new();
   if db.is_err() {
     panic!("Failed to initialize database: {:?}", db.err());
   }
+  let config = ServerConfig::load(cli.config.as_deref(), cli.defaults, cli.overrides);
+  if config.is_err() {
+    panic!("Failed to load config: {:?}", config.err());
+  }
+  let warehouse = Warehouse::new(warehouse_root.clone());
+  if warehouse.is_err() {
+    panic!("Failed to initialize warehouse: {:?}", warehouse.err());
+  }
+  let config = config.unwrap();
+  let default_catalog = Catalog {
+    db: db.unwrap(),
+    warehouse: warehouse.unwrap(),
+  };
+  let catalogs = Catalogs::new(
+    default_catalog,
+    &config,
+    &db_root,
+    &warehouse_root,
+    cli.backend,
+  );
+  if catalogs.is_err() {
+    panic!("Failed to initialize catalogs: {:?}", catalogs.err());
+  }
 
   rocket::build()
-    .manage(db.unwrap())
+    .manage(catalogs.unwrap())
     .manage(table_metedata_generator)
+    .manage(config)
+    .attach(prefix::stage())
     .attach(namespace::stage())
     .attach(catches::stage())
     .mount(
@@ -37,11 +66,23 @@ pub fn rocket() -> _ {
         table::register_table,
         table::get_table,
         table::post_table,
+        table::commit_transaction,
         table::delete_table,
+        table::get_purge,
         table::head_table,
         table::rename_table,
+        view::get_views_by_namespace,
+        view::post_view_by_namespace,
+        view::get_view,
+        view::post_view,
+        view::delete_view,
+        view::head_view,
+        view::rename_view,
         metric::post_metrics,
+        metric::get_metrics,
         config::get_config,
+        admin::list_dropped,
+        admin::restore_dropped,
       ],
     )
 }
@@ -53,6 +94,6 @@ mod test {
   #[rocket::async_test]
   async fn test_create_server() {
     let client = Client::tracked(crate::rocket()).await;
-    assert_eq!(client.is_ok(), true);
+    assert!(client.is_ok());
   }
 }
@@ -1,2 +1,3 @@
 pub mod catches;
+pub mod prefix;
 pub mod routes;
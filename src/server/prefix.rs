@@ -0,0 +1,269 @@
+use crate::catalog::file_io::Warehouse;
+use crate::common::result::{ErrorType, Location, Result};
+use crate::db::{Backend, DB};
+use crate::err;
+use crate::server::routes::config::ServerConfig;
+use rocket::fairing::AdHoc;
+use rocket::http::uri::Origin;
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+// first path segments of the routes, a prefix cannot use them
+const RESERVED_PREFIXES: [&str; 6] = [
+  "admin",
+  "config",
+  "namespaces",
+  "tables",
+  "transactions",
+  "views",
+];
+
+pub struct Catalog {
+  pub db: DB,
+  pub warehouse: Warehouse,
+}
+
+/// All the catalogs served by this process. Requests to `/v1/...` go to the default catalog and
+/// requests to `/v1/<prefix>/...` go to the catalog of that warehouse.
+pub struct Catalogs {
+  default: Catalog,
+  prefixed: HashMap<String, Catalog>,
+}
+
+impl Catalogs {
+  // Every warehouse of the config file gets its own catalog, stored under `db-root` and
+  // `warehouse-root` when set. Otherwise its db is in a sub directory of the default db root and
+  // its files in a sibling of the default warehouse root, see `prefixed_warehouse_root`.
+  pub fn new(
+    default: Catalog,
+    config: &ServerConfig,
+    db_root: &Path,
+    warehouse_root: &Path,
+    backend: Backend,
+  ) -> Result<Catalogs> {
+    let mut prefixed = HashMap::new();
+    for (name, warehouse_config) in config.warehouses.iter() {
+      if RESERVED_PREFIXES.contains(&name.as_str())
+        || !name
+          .chars()
+          .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+      {
+        return err!(
+          ErrorType::BadRequest,
+          Location::Request,
+          format!("Invalid warehouse name {}", name)
+        );
+      }
+      let catalog = Catalog {
+        db: DB::new(
+          warehouse_config
+            .db_root
+            .clone()
+            .unwrap_or_else(|| db_root.join(name)),
+          backend,
+          name,
+        )?,
+        warehouse: Warehouse::new(match &warehouse_config.warehouse_root {
+          Some(root) => root.clone(),
+          None => prefixed_warehouse_root(warehouse_root, name)?,
+        })?,
+      };
+      prefixed.insert(name.clone(), catalog);
+    }
+    Ok(Catalogs { default, prefixed })
+  }
+
+  fn get(&self, prefix: &Option<String>) -> Option<&Catalog> {
+    match prefix {
+      None => Some(&self.default),
+      Some(prefix) => self.prefixed.get(prefix),
+    }
+  }
+}
+
+// `<default root>-<name>`. A sub directory of the default root would be the directory of the
+// namespace `<name>` of the default catalog, and the catalogs would share their files.
+fn prefixed_warehouse_root(warehouse_root: &Path, name: &str) -> Result<PathBuf> {
+  let root = fs::canonicalize(warehouse_root).ok();
+  match root
+    .as_deref()
+    .and_then(|root| Some((root, root.file_name()?)))
+  {
+    Some((root, file_name)) => {
+      let mut file_name = file_name.to_os_string();
+      file_name.push(format!("-{}", name));
+      Ok(root.with_file_name(file_name))
+    }
+    None => err!(
+      ErrorType::BadRequest,
+      Location::Request,
+      format!(
+        "Warehouse {} needs a warehouse-root, none can be derived from {:?}",
+        name, warehouse_root
+      )
+    ),
+  }
+}
+
+// prefix of the current request, cached by the fairing before the prefix is removed
+struct Prefix(Option<String>);
+
+fn strip_prefix(req: &mut Request<'_>) {
+  let catalogs = match req.rocket().state::<Catalogs>() {
+    Some(catalogs) => catalogs,
+    None => return,
+  };
+  // prefixes only use characters that are never percent-encoded, the raw path can be used as is
+  let path = req.uri().path().as_str();
+  let (prefix, rest) = match path.strip_prefix("/v1/").and_then(|x| x.split_once('/')) {
+    Some((prefix, rest)) if !rest.is_empty() && catalogs.prefixed.contains_key(prefix) => {
+      (prefix.to_string(), rest)
+    }
+    _ => return,
+  };
+  let uri = match req.uri().query() {
+    Some(query) => format!("/v1/{}?{}", rest, query),
+    None => format!("/v1/{}", rest),
+  };
+  if let Ok(origin) = Origin::parse_owned(uri) {
+    req.local_cache(|| Prefix(Some(prefix)));
+    req.set_uri(origin);
+  }
+}
+
+pub fn stage() -> AdHoc {
+  AdHoc::on_request("catalog prefix", |req, _| {
+    Box::pin(async move { strip_prefix(req) })
+  })
+}
+
+fn catalog<'r>(req: &'r Request<'_>) -> Option<&'r Catalog> {
+  let prefix = &req.local_cache(|| Prefix(None)).0;
+  req.rocket().state::<Catalogs>()?.get(prefix)
+}
+
+/// The db of the catalog selected by the prefix of the request.
+pub struct CatalogDB<'r>(&'r DB);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CatalogDB<'r> {
+  type Error = ();
+
+  async fn from_request(req: &'r Request<'_>) -> Outcome<Self, ()> {
+    match catalog(req) {
+      Some(catalog) => Outcome::Success(CatalogDB(&catalog.db)),
+      None => Outcome::Error((Status::InternalServerError, ())),
+    }
+  }
+}
+
+impl Deref for CatalogDB<'_> {
+  type Target = DB;
+
+  fn deref(&self) -> &DB {
+    self.0
+  }
+}
+
+/// The warehouse of the catalog selected by the prefix of the request.
+pub struct CatalogWarehouse<'r>(&'r Warehouse);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for CatalogWarehouse<'r> {
+  type Error = ();
+
+  async fn from_request(req: &'r Request<'_>) -> Outcome<Self, ()> {
+    match catalog(req) {
+      Some(catalog) => Outcome::Success(CatalogWarehouse(&catalog.warehouse)),
+      None => Outcome::Error((Status::InternalServerError, ())),
+    }
+  }
+}
+
+impl Deref for CatalogWarehouse<'_> {
+  type Target = Warehouse;
+
+  fn deref(&self) -> &Warehouse {
+    self.0
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use crate::server::routes::config::ServerConfig;
+  use crate::server::routes::namespace::CreateNamespaceRequest;
+  use crate::table::test::{create_mock_client_with_config, mock_create_table_request};
+  use rocket::http::{ContentType, Status};
+  use rocket::serde::json::{json, Value};
+
+  #[rocket::async_test]
+  async fn test_prefixed_catalogs() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let config: ServerConfig = serde_json::from_value(json!({
+      "warehouses": { "dev": {}, "prod": { "overrides": { "owner": "ops" } } },
+    }))
+    .unwrap();
+    let client = create_mock_client_with_config(temp_dir.path().to_path_buf(), config).await;
+
+    let response = client.get("/v1/config?warehouse=dev").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["overrides"]["prefix"], "dev");
+
+    let create_namespace = |levels: &[&str]| {
+      serde_json::to_vec(&CreateNamespaceRequest {
+        namespace: levels.iter().map(|x| x.to_string()).collect(),
+        properties: None,
+      })
+      .unwrap()
+    };
+    for levels in [vec!["shared"], vec!["shared", "nested"]] {
+      let response = client
+        .post("/v1/dev/namespaces")
+        .header(ContentType::JSON)
+        .body(create_namespace(&levels))
+        .dispatch()
+        .await;
+      assert_eq!(response.status(), Status::Ok);
+    }
+    let response = client
+      .post("/v1/dev/namespaces/shared%1Fnested/tables")
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&mock_create_table_request("events")).unwrap())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    let location = body["metadata"]["location"].as_str().unwrap();
+    assert!(location.ends_with("/warehouse-dev/shared/nested/events"));
+
+    // the other catalogs do not see the namespace
+    let response = client.get("/v1/dev/namespaces/shared").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    for url in ["/v1/prod/namespaces/shared", "/v1/namespaces/shared"] {
+      let response = client.get(url).dispatch().await;
+      assert_eq!(response.status(), Status::NotFound);
+    }
+    let response = client
+      .get("/v1/dev/namespaces?parent=shared")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let response = client
+      .get("/v1/dev/namespaces/shared%1Fnested/tables/events")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client.get("/v1/unknown/namespaces/shared").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+    assert!(temp_dir
+      .path()
+      .join("prod")
+      .join("catalog.namespace")
+      .exists());
+  }
+}
@@ -0,0 +1,115 @@
+use crate::catalog::dropped::Dropped;
+use crate::catalog::metric::Metric;
+use crate::common::result::{EmptyResult, Result};
+use crate::ok_empty;
+use crate::server::prefix::CatalogDB;
+use crate::server::routes::response::ListDroppedResponse;
+use rocket::serde::json::Json;
+
+/// List the dropped tables and namespaces that can still be restored, oldest drop first
+#[get("/admin/dropped")]
+pub fn list_dropped(db: CatalogDB<'_>) -> Result<Json<ListDroppedResponse>> {
+  let dropped = Dropped::list(db.conn())?;
+  Ok(Json(ListDroppedResponse { dropped }))
+}
+
+/// Restore a dropped table or namespace under its old name
+#[post("/admin/dropped/<id>/restore")]
+pub fn restore_dropped(id: &str, db: CatalogDB<'_>) -> EmptyResult {
+  let dropped = Dropped::restore(db.conn(), id)?;
+  Metric::restore_dropped(db.metrics_conn(), &dropped)?;
+  ok_empty!()
+}
+
+#[cfg(test)]
+mod test {
+  use crate::table::test::{
+    create_mock_client, create_namespace_and_table, mock_create_table_request,
+  };
+  use rocket::http::{ContentType, Status};
+  use rocket::serde::json::{json, Value};
+
+  #[rocket::async_test]
+  async fn test_restore_dropped() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let metadata = create_namespace_and_table(&client, "a", "t").await;
+    // a namespace with tables cannot be dropped
+    let response = client.delete("/v1/namespaces/a").dispatch().await;
+    assert_eq!(response.status(), Status::Conflict);
+    let response = client.delete("/v1/namespaces/a/tables/t").dispatch().await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.get("/v1/namespaces/a/tables/t").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    let response = client.get("/v1/admin/dropped").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    let dropped = &body["dropped"][0];
+    assert_eq!(dropped["type"], "table");
+    assert_eq!(dropped["namespace"], json!(["a"]));
+    assert_eq!(dropped["name"], "t");
+    assert!(dropped["expires-ms"].as_i64() > dropped["dropped-ms"].as_i64());
+    let restore_url = format!(
+      "/v1/admin/dropped/{}/restore",
+      dropped["id"].as_str().unwrap()
+    );
+
+    // the name was taken since the drop
+    let response = client
+      .post("/v1/namespaces/a/tables")
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&mock_create_table_request("t")).unwrap())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.post(&restore_url).dispatch().await;
+    assert_eq!(response.status(), Status::Conflict);
+    let response = client.delete("/v1/namespaces/a/tables/t").dispatch().await;
+    assert_eq!(response.status(), Status::NoContent);
+
+    let response = client.post(&restore_url).dispatch().await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.get("/v1/namespaces/a/tables/t").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["metadata"]["table-uuid"], metadata["table-uuid"]);
+    let response = client.post(&restore_url).dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.get("/v1/admin/dropped").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["dropped"].as_array().unwrap().len(), 1);
+
+    let response = client
+      .post("/v1/namespaces")
+      .header(ContentType::JSON)
+      .body(json!({ "namespace": ["b"], "properties": { "owner": "x" } }).to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let properties = client.get("/v1/namespaces/b").dispatch().await;
+    let properties = properties.into_json::<Value>().await.unwrap();
+    let response = client.delete("/v1/namespaces/b").dispatch().await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.get("/v1/admin/dropped").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    let dropped = body["dropped"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .find(|x| x["type"] == "namespace")
+      .unwrap();
+    assert_eq!(dropped["namespace"], json!(["b"]));
+    assert!(dropped.get("name").is_none());
+    let restore_url = format!(
+      "/v1/admin/dropped/{}/restore",
+      dropped["id"].as_str().unwrap()
+    );
+    let response = client.post(&restore_url).dispatch().await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.get("/v1/namespaces/b").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<Value>().await.unwrap(), properties);
+  }
+}
@@ -1,5 +1,6 @@
 // use crate::catalog::namespace::Namespace;
 use rocket::serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
@@ -12,7 +13,7 @@ pub struct TableIdentifier {
 #[serde(crate = "rocket::serde")]
 pub struct NamespaceResponse(pub Vec<String>);
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "rocket::serde")]
 #[serde(untagged)]
 pub enum Type {
@@ -22,22 +23,23 @@ pub enum Type {
   Map(Box<MapType>),
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "rocket::serde")]
-pub struct PrimitiveType(String);
+pub struct PrimitiveType(pub String);
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "rocket::serde")]
 pub struct StructField {
-  id: i32,
-  name: String,
-  #[serde(flatten)]
-  type_: Type,
-  required: bool,
-  // doc: Option<String>,
+  pub id: i32,
+  pub name: String,
+  #[serde(rename = "type")]
+  pub type_: Type,
+  pub required: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub doc: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "rocket::serde")]
 pub struct StructType {
   #[serde(rename = "type")]
@@ -45,83 +47,488 @@ pub struct StructType {
   pub fields: Vec<StructField>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
 pub struct ListType {
   #[serde(rename = "type")]
-  type_: String,
-  element_id: i32,
-  #[serde(flatten)]
-  element: Type,
-  element_required: bool,
+  pub type_: String,
+  pub element_id: i32,
+  pub element: Type,
+  pub element_required: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
 pub struct MapType {
   #[serde(rename = "type")]
-  type_: String,
-  key_id: i32,
-  #[serde(flatten)]
-  key: Type,
-  value_id: i32,
-  #[serde(flatten)]
-  value: Type,
-  value_required: bool,
+  pub type_: String,
+  pub key_id: i32,
+  pub key: Type,
+  pub value_id: i32,
+  pub value: Type,
+  pub value_required: bool,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
 pub struct Schema {
-  // #[serde(flatten)]
-  // pub struct_type: StructType, // TODO: update!
+  #[serde(flatten)]
+  pub struct_type: StructType,
+  #[serde(default, alias = "schema_id")]
   pub schema_id: i32,
+  #[serde(
+    default,
+    alias = "identifier_field_ids",
+    skip_serializing_if = "Vec::is_empty"
+  )]
   pub identifier_field_ids: Vec<i32>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct PartitionField {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub field_id: Option<i32>,
+  pub source_id: i32,
+  pub name: String,
+  pub transform: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct PartitionSpec {
+  #[serde(default)]
+  pub spec_id: i32,
+  pub fields: Vec<PartitionField>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct SortField {
+  pub source_id: i32,
+  pub transform: String,
+  pub direction: String,
+  pub null_order: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct SortOrder {
+  #[serde(default)]
+  pub order_id: i32,
+  pub fields: Vec<SortField>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct Snapshot {
+  pub snapshot_id: i64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub parent_snapshot_id: Option<i64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub sequence_number: Option<i64>,
+  pub timestamp_ms: i64,
+  // v1 tables may list the manifests directly instead of using a manifest list
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub manifest_list: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub manifests: Option<Vec<String>>,
+  pub summary: HashMap<String, String>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub schema_id: Option<i32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotReference {
+  #[serde(rename = "type")]
+  pub type_: String,
+  pub snapshot_id: i64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub max_ref_age_ms: Option<i64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub max_snapshot_age_ms: Option<i64>,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub min_snapshots_to_keep: Option<i32>,
+}
+
+pub type SnapshotReferences = HashMap<String, SnapshotReference>;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct SnapshotLogEntry {
+  pub snapshot_id: i64,
+  pub timestamp_ms: i64,
+}
+
+pub type SnapshotLog = Vec<SnapshotLogEntry>;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct MetadataLogEntry {
+  pub metadata_file: String,
+  pub timestamp_ms: i64,
+}
+
+pub type MetadataLog = Vec<MetadataLogEntry>;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct BlobMetadata {
+  #[serde(rename = "type")]
+  pub type_: String,
+  pub snapshot_id: i64,
+  pub sequence_number: i64,
+  pub fields: Vec<i32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub properties: Option<HashMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct StatisticsFile {
+  pub snapshot_id: i64,
+  pub statistics_path: String,
+  pub file_size_in_bytes: i64,
+  pub file_footer_size_in_bytes: i64,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub key_metadata: Option<String>,
+  pub blob_metadata: Vec<BlobMetadata>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct PartitionStatisticsFile {
+  pub snapshot_id: i64,
+  pub statistics_path: String,
+  pub file_size_in_bytes: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
 pub struct TableMetadata {
+  #[serde(alias = "format_version")]
   pub format_version: i32,
+  #[serde(alias = "table_uuid")]
   pub table_uuid: String,
-  // pub location: Option<String>,
-  // pub last_updated_ms: Option<i64>,
-  // pub properties: HashMap<String, String>,
-  // pub schemas: Vec<Schema>,
-  // pub current_schema_id: Option<i32>,
-  // pub last_column_id: Option<i32>,
-  // pub partition_specs: Vec<PartitionSpec>,
-  // pub default_spec_id: Option<i32>,
-  // pub last_partition_id: Option<i32>,
-  // pub sort_orders: Vec<SortOrder>,
-  // pub default_sort_order_id: Option<i32>,
-  // pub snapshots: Vec<Snapshot>,
-  // pub refs: SnapshotReferences,
-  // pub current_snapshot_id: Option<i64>,
-  // pub last_sequence_number: Option<i64>,
-  // pub snapshot_log: SnapshotLog,
-  // pub metadata_log: MetadataLog,
-}
-
-use std::sync::atomic::{AtomicUsize, Ordering};
-
-pub struct TableMetadataAtomicIncr {
-  table_uuid_counter: AtomicUsize,
-}
-
-impl TableMetadataAtomicIncr {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub location: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub last_updated_ms: Option<i64>,
+  #[serde(default)]
+  pub properties: HashMap<String, String>,
+  // v1 only, superseded by `schemas`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub schema: Option<Schema>,
+  #[serde(default)]
+  pub schemas: Vec<Schema>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub current_schema_id: Option<i32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub last_column_id: Option<i32>,
+  // v1 only, superseded by `partition-specs`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub partition_spec: Option<Vec<PartitionField>>,
+  #[serde(default)]
+  pub partition_specs: Vec<PartitionSpec>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub default_spec_id: Option<i32>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub last_partition_id: Option<i32>,
+  #[serde(default)]
+  pub sort_orders: Vec<SortOrder>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub default_sort_order_id: Option<i32>,
+  #[serde(default)]
+  pub snapshots: Vec<Snapshot>,
+  #[serde(default)]
+  pub refs: SnapshotReferences,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub current_snapshot_id: Option<i64>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub last_sequence_number: Option<i64>,
+  #[serde(default)]
+  pub snapshot_log: SnapshotLog,
+  #[serde(default)]
+  pub metadata_log: MetadataLog,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub statistics: Vec<StatisticsFile>,
+  #[serde(default, skip_serializing_if = "Vec::is_empty")]
+  pub partition_statistics: Vec<PartitionStatisticsFile>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct ViewMetadata {
+  pub view_uuid: String,
+  pub format_version: i32,
+  pub location: String,
+  pub current_version_id: i32,
+  pub versions: Vec<ViewVersion>,
+  // every change of the current version, oldest first
+  pub version_log: Vec<ViewHistoryEntry>,
+  pub schemas: Vec<Schema>,
+  #[serde(default)]
+  pub properties: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct ViewVersion {
+  pub version_id: i32,
+  pub timestamp_ms: i64,
+  // -1 refers to the schema added last in the same commit
+  pub schema_id: i32,
+  #[serde(default)]
+  pub summary: HashMap<String, String>,
+  pub representations: Vec<ViewRepresentation>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub default_catalog: Option<String>,
+  pub default_namespace: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(tag = "type")]
+pub enum ViewRepresentation {
+  #[serde(rename = "sql")]
+  Sql(SqlViewRepresentation),
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+pub struct SqlViewRepresentation {
+  pub sql: String,
+  pub dialect: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct ViewHistoryEntry {
+  pub version_id: i32,
+  pub timestamp_ms: i64,
+}
+
+pub struct TableMetadataGenerator;
+
+impl TableMetadataGenerator {
   pub fn new() -> Self {
-    TableMetadataAtomicIncr {
-      table_uuid_counter: AtomicUsize::new(0),
-    }
+    TableMetadataGenerator
   }
 
+  // Table uuids are random (v4) so they stay unique across restarts of the catalog.
   pub fn generate_table_metadata(&self, format_version: i32) -> TableMetadata {
-    let uuid = self.table_uuid_counter.fetch_add(1, Ordering::SeqCst);
-    let table_uuid = format!("uuid{}", uuid); // Generate UUID based on the counter value
+    let table_uuid = uuid::Uuid::new_v4().to_string();
     TableMetadata {
       format_version,
       table_uuid,
+      location: None,
+      last_updated_ms: None,
+      properties: HashMap::new(),
+      schema: None,
+      schemas: vec![],
+      current_schema_id: None,
+      last_column_id: None,
+      partition_spec: None,
+      partition_specs: vec![],
+      default_spec_id: None,
+      last_partition_id: None,
+      sort_orders: vec![],
+      default_sort_order_id: None,
+      snapshots: vec![],
+      refs: HashMap::new(),
+      current_snapshot_id: None,
+      last_sequence_number: None,
+      snapshot_log: vec![],
+      metadata_log: vec![],
+      statistics: vec![],
+      partition_statistics: vec![],
     }
   }
 }
+
+#[cfg(test)]
+mod test {
+  use crate::catalog::table::Table;
+  use rocket::serde::json::{json, Value};
+
+  fn round_trip(metadata: Value) {
+    let table = Table {
+      name: "t".to_string(),
+      metadata: serde_json::from_value(metadata.clone()).expect("failed to parse metadata"),
+      metadata_location: None,
+    };
+    let stored = serde_json::to_string(&table).unwrap();
+    let loaded: Table = serde_json::from_str(&stored).unwrap();
+    assert_eq!(serde_json::to_value(&loaded.metadata).unwrap(), metadata);
+  }
+
+  #[test]
+  fn test_table_metadata_v2_round_trip() {
+    round_trip(json!({
+      "format-version": 2,
+      "table-uuid": "9c12d441-03fe-4693-9a96-a0705ddf69c1",
+      "location": "s3://bucket/test/location",
+      "last-sequence-number": 34,
+      "last-updated-ms": 1602638573590i64,
+      "last-column-id": 5,
+      "current-schema-id": 1,
+      "schemas": [
+        {
+          "type": "struct",
+          "schema-id": 0,
+          "fields": [{ "id": 1, "name": "x", "required": true, "type": "long" }]
+        },
+        {
+          "type": "struct",
+          "schema-id": 1,
+          "identifier-field-ids": [1, 2],
+          "fields": [
+            { "id": 1, "name": "x", "required": true, "type": "long" },
+            { "id": 2, "name": "y", "required": true, "type": "long", "doc": "comment" },
+            {
+              "id": 3,
+              "name": "z",
+              "required": false,
+              "type": {
+                "type": "list",
+                "element-id": 4,
+                "element-required": true,
+                "element": {
+                  "type": "map",
+                  "key-id": 5,
+                  "key": "string",
+                  "value-id": 6,
+                  "value": "decimal(10,2)",
+                  "value-required": false
+                }
+              }
+            }
+          ]
+        }
+      ],
+      "default-spec-id": 0,
+      "partition-specs": [
+        {
+          "spec-id": 0,
+          "fields": [{ "name": "x", "transform": "identity", "source-id": 1, "field-id": 1000 }]
+        }
+      ],
+      "last-partition-id": 1000,
+      "default-sort-order-id": 3,
+      "sort-orders": [
+        {
+          "order-id": 3,
+          "fields": [
+            { "transform": "identity", "source-id": 2, "direction": "asc", "null-order": "nulls-first" },
+            { "transform": "bucket[4]", "source-id": 3, "direction": "desc", "null-order": "nulls-last" }
+          ]
+        }
+      ],
+      "properties": { "read.split.target.size": "134217728" },
+      "current-snapshot-id": 3055729675574597004i64,
+      "snapshots": [
+        {
+          "snapshot-id": 3051729675574597004i64,
+          "timestamp-ms": 1515100955770i64,
+          "sequence-number": 0,
+          "summary": { "operation": "append" },
+          "manifest-list": "s3://a/b/1.avro"
+        },
+        {
+          "snapshot-id": 3055729675574597004i64,
+          "parent-snapshot-id": 3051729675574597004i64,
+          "timestamp-ms": 1555100955770i64,
+          "sequence-number": 1,
+          "summary": { "operation": "append" },
+          "manifest-list": "s3://a/b/2.avro",
+          "schema-id": 1
+        }
+      ],
+      "refs": {
+        "main": { "type": "branch", "snapshot-id": 3055729675574597004i64 },
+        "test": { "type": "tag", "snapshot-id": 3051729675574597004i64, "max-ref-age-ms": 10000000 }
+      },
+      "snapshot-log": [
+        { "snapshot-id": 3051729675574597004i64, "timestamp-ms": 1515100955770i64 },
+        { "snapshot-id": 3055729675574597004i64, "timestamp-ms": 1555100955770i64 }
+      ],
+      "metadata-log": [
+        { "metadata-file": "s3://bucket/.../v1.json", "timestamp-ms": 1515100 }
+      ],
+      "statistics": [
+        {
+          "snapshot-id": 3055729675574597004i64,
+          "statistics-path": "s3://a/b/stats.puffin",
+          "file-size-in-bytes": 413,
+          "file-footer-size-in-bytes": 42,
+          "blob-metadata": [
+            {
+              "type": "ndv",
+              "snapshot-id": 3055729675574597004i64,
+              "sequence-number": 1,
+              "fields": [1]
+            }
+          ]
+        }
+      ]
+    }));
+  }
+
+  #[test]
+  fn test_table_metadata_v1_round_trip() {
+    round_trip(json!({
+      "format-version": 1,
+      "table-uuid": "d20125c8-7284-442c-9aea-15fee620737c",
+      "location": "s3://bucket/test/location",
+      "last-updated-ms": 1602638573874i64,
+      "last-column-id": 3,
+      "schema": {
+        "type": "struct",
+        "schema-id": 0,
+        "fields": [
+          { "id": 1, "name": "x", "required": true, "type": "long" },
+          { "id": 2, "name": "y", "required": true, "type": "long", "doc": "comment" },
+          { "id": 3, "name": "z", "required": true, "type": "long" }
+        ]
+      },
+      "schemas": [],
+      "partition-spec": [
+        { "name": "x", "transform": "identity", "source-id": 1, "field-id": 1000 }
+      ],
+      "partition-specs": [],
+      "properties": {},
+      "current-snapshot-id": -1,
+      "snapshots": [
+        {
+          "snapshot-id": 1,
+          "timestamp-ms": 1515100955770i64,
+          "summary": { "operation": "append" },
+          "manifests": ["s3://a/b/1.avro"]
+        }
+      ],
+      "refs": {},
+      "snapshot-log": [],
+      "metadata-log": [],
+      "sort-orders": []
+    }));
+  }
+}
@@ -1,4 +1,206 @@
-#[get("/config")]
-pub fn get_config() {
-  todo!("get_config")
+use crate::catalog::dropped::DEFAULT_RETENTION_MS;
+use crate::common::result::{ErrorType, Location, Result};
+use crate::err;
+use crate::server::routes::response::CatalogConfig;
+use rocket::serde::{json::Json, Deserialize};
+use rocket::State;
+use std::collections::HashMap;
+use std::{
+  fs,
+  path::{Path, PathBuf},
+};
+
+/// Catalog properties sent to clients, loaded from the config file and the command line.
+#[derive(Deserialize, Default)]
+#[serde(crate = "rocket::serde")]
+pub struct ServerConfig {
+  #[serde(flatten)]
+  pub catalog: CatalogConfig,
+  // every warehouse is a separate catalog, served under the warehouse name as prefix
+  #[serde(default)]
+  pub warehouses: HashMap<String, WarehouseConfig>,
+  // how long dropped tables and namespaces can be restored before they are forgotten
+  #[serde(default, rename = "drop-retention-seconds")]
+  pub drop_retention_seconds: Option<u64>,
+  // properties given on the command line, merged last so they win over the warehouse ones too
+  #[serde(skip)]
+  pub command_line: CatalogConfig,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct WarehouseConfig {
+  // properties for clients of this warehouse, merged over the catalog ones
+  #[serde(flatten)]
+  pub catalog: CatalogConfig,
+  #[serde(default)]
+  pub db_root: Option<PathBuf>,
+  #[serde(default)]
+  pub warehouse_root: Option<PathBuf>,
+}
+
+impl ServerConfig {
+  pub fn drop_retention_ms(&self) -> i64 {
+    match self.drop_retention_seconds {
+      Some(seconds) => i64::try_from(seconds.saturating_mul(1000)).unwrap_or(i64::MAX),
+      None => DEFAULT_RETENTION_MS,
+    }
+  }
+
+  // Properties given on the command line take precedence over the ones from the config file.
+  pub fn load(
+    path: Option<&Path>,
+    defaults: Vec<(String, String)>,
+    overrides: Vec<(String, String)>,
+  ) -> Result<ServerConfig> {
+    let mut config = match path {
+      None => ServerConfig::default(),
+      Some(path) => {
+        let content = match fs::read_to_string(path) {
+          Ok(content) => content,
+          Err(e) => {
+            return err!(
+              ErrorType::InternalError,
+              Location::Request,
+              format!("Failed to read config file {:?}: {}", path, e)
+            )
+          }
+        };
+        match serde_json::from_str(&content) {
+          Ok(config) => config,
+          Err(e) => {
+            return err!(
+              ErrorType::InternalError,
+              Location::Request,
+              format!("Invalid config file {:?}: {}", path, e)
+            )
+          }
+        }
+      }
+    };
+    config.command_line = CatalogConfig {
+      defaults: defaults.into_iter().collect(),
+      overrides: overrides.into_iter().collect(),
+    };
+    config
+      .catalog
+      .defaults
+      .extend(config.command_line.defaults.clone());
+    config
+      .catalog
+      .overrides
+      .extend(config.command_line.overrides.clone());
+    Ok(config)
+  }
+}
+
+/// List all catalog configuration settings
+#[get("/config?<warehouse>")]
+pub fn get_config(
+  warehouse: Option<&str>,
+  config: &State<ServerConfig>,
+) -> Result<Json<CatalogConfig>> {
+  let mut catalog = config.catalog.clone();
+  if let Some(name) = warehouse {
+    let warehouse_config = match config.warehouses.get(name) {
+      Some(warehouse_config) => warehouse_config,
+      None => {
+        return err!(
+          ErrorType::NotFound,
+          Location::Request,
+          format!("Warehouse {} not found", name)
+        )
+      }
+    };
+    catalog
+      .defaults
+      .extend(warehouse_config.catalog.defaults.clone());
+    catalog
+      .overrides
+      .extend(warehouse_config.catalog.overrides.clone());
+    catalog
+      .defaults
+      .extend(config.command_line.defaults.clone());
+    catalog
+      .overrides
+      .extend(config.command_line.overrides.clone());
+    // clients send the following requests to `/v1/<prefix>/...`
+    catalog
+      .overrides
+      .insert("prefix".to_string(), name.to_string());
+  }
+  Ok(Json(catalog))
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+  use crate::server::catches;
+  use rocket::http::Status;
+  use rocket::local::asynchronous::Client;
+  use rocket::serde::json::{json, Value};
+
+  #[rocket::async_test]
+  async fn test_get_config() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let config_path = temp_dir.path().join("config.json");
+    let config_file = json!({
+      "defaults": { "write.format.default": "parquet", "clients": "4", "codec": "zstd" },
+      "overrides": { "cache-enabled": "false" },
+      "warehouses": {
+        "analytics": {
+          "defaults": { "write.format.default": "orc", "codec": "gzip" },
+          "overrides": { "warehouse": "analytics" },
+        },
+      },
+    });
+    fs::write(&config_path, config_file.to_string()).unwrap();
+    // the command line wins over the config file, warehouses included
+    let config = ServerConfig::load(
+      Some(&config_path),
+      vec![
+        ("clients".to_string(), "8".to_string()),
+        ("write.format.default".to_string(), "avro".to_string()),
+      ],
+      vec![("warehouse".to_string(), "shared".to_string())],
+    )
+    .expect("failed to load the config");
+
+    let rocket = rocket::build()
+      .manage(config)
+      .attach(catches::stage())
+      .mount("/v1", routes![get_config]);
+    let client = Client::tracked(rocket).await.unwrap();
+
+    let response = client.get("/v1/config").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+      response.into_json::<Value>().await.unwrap(),
+      json!({
+        "defaults": { "write.format.default": "avro", "clients": "8", "codec": "zstd" },
+        "overrides": { "cache-enabled": "false", "warehouse": "shared" },
+      })
+    );
+
+    let response = client
+      .get("/v1/config?warehouse=analytics")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+      response.into_json::<Value>().await.unwrap(),
+      json!({
+        "defaults": { "write.format.default": "avro", "clients": "8", "codec": "gzip" },
+        "overrides": {
+          "cache-enabled": "false",
+          "warehouse": "shared",
+          "prefix": "analytics",
+        },
+      })
+    );
+
+    let response = client.get("/v1/config?warehouse=unknown").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+  }
 }
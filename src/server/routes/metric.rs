@@ -1,5 +1,230 @@
+use crate::catalog::metric::{Metric, MAX_REPORTS_PER_TABLE};
+use crate::common::result::{EmptyResult, Result};
+use crate::ok_empty;
+use crate::server::prefix::CatalogDB;
+use crate::server::routes::namespace::NamespaceParam;
+use crate::server::routes::request::ReportMetricsRequest;
+use crate::server::routes::response::ListMetricsResponse;
+use rocket::serde::json::Json;
+
 /// Send a metrics report to this endpoint to be processed by the backend
-#[post("/namespaces/<namespace>/tables/<table>/metrics")]
-pub fn post_metrics(namespace: &str, table: &str) {
-  todo!("post_metrics {} {}", namespace, table)
+#[post("/namespaces/<namespace>/tables/<table>/metrics", data = "<report>")]
+pub fn post_metrics(
+  namespace: NamespaceParam,
+  table: &str,
+  report: Json<ReportMetricsRequest>,
+  db: CatalogDB<'_>,
+) -> EmptyResult {
+  let conn = db.conn();
+  let metrics_conn = db.metrics_conn();
+  Metric::report(
+    conn,
+    metrics_conn,
+    &namespace.0,
+    table.to_string(),
+    report.into_inner(),
+  )?;
+  ok_empty!()
+}
+
+/// List the most recent metrics reports of a table, newest first
+#[get("/namespaces/<namespace>/tables/<table>/metrics?<limit>")]
+pub fn get_metrics(
+  namespace: NamespaceParam,
+  table: &str,
+  limit: Option<usize>,
+  db: CatalogDB<'_>,
+) -> Result<Json<ListMetricsResponse>> {
+  let conn = db.conn();
+  let metrics_conn = db.metrics_conn();
+  let reports = Metric::list(
+    conn,
+    metrics_conn,
+    &namespace.0,
+    table.to_string(),
+    limit.unwrap_or(MAX_REPORTS_PER_TABLE),
+  )?;
+  Ok(Json(ListMetricsResponse { reports }))
+}
+
+#[cfg(test)]
+mod test {
+  use crate::table::test::{
+    create_mock_client, create_namespace_and_table, mock_create_table_request,
+  };
+  use rocket::http::{ContentType, Status};
+  use rocket::serde::json::{json, Value};
+
+  #[rocket::async_test]
+  async fn test_metrics() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let namespace_name = "namespacenametest";
+    let table_name = "tablenametest";
+    create_namespace_and_table(&client, namespace_name, table_name).await;
+    let table_url = format!("/v1/namespaces/{}/tables/{}", namespace_name, table_name);
+    let response = client
+      .post(&table_url)
+      .header(ContentType::JSON)
+      .body(
+        json!({
+          "requirements": [],
+          "updates": [{
+            "action": "add-snapshot",
+            "snapshot": {
+              "snapshot-id": 7,
+              "sequence-number": 1,
+              "timestamp-ms": 1700000000000i64,
+              "manifest-list": "/tmp/snap-7.avro",
+              "summary": { "operation": "append" },
+            },
+          }],
+        })
+        .to_string(),
+      )
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let url = format!("{}/metrics", table_url);
+    let scan_report = json!({
+      "report-type": "scan-report",
+      "table-name": format!("rest.{}.{}", namespace_name, table_name),
+      "snapshot-id": 7,
+      "filter": true,
+      "schema-id": 0,
+      "projected-field-ids": [1, 2],
+      "projected-field-names": ["id", "data"],
+      "metrics": {
+        "total-planning-duration": { "count": 1, "time-unit": "nanoseconds", "total-duration": 2644235116i64 },
+        "result-data-files": { "unit": "count", "value": 3 },
+      },
+    });
+    let commit_report = json!({
+      "report-type": "commit-report",
+      "table-name": table_name,
+      "snapshot-id": 7,
+      "sequence-number": 1,
+      "operation": "append",
+      "metrics": { "added-data-files": { "unit": "count", "value": 3 } },
+      "metadata": { "engine": "spark" },
+    });
+    for report in [&scan_report, &commit_report] {
+      let response = client
+        .post(&url)
+        .header(ContentType::JSON)
+        .body(report.to_string())
+        .dispatch()
+        .await;
+      assert_eq!(response.status(), Status::NoContent);
+    }
+
+    let response = client.get(&url).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    let reports = body["reports"].as_array().unwrap();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0]["report-type"], "commit-report");
+    assert_eq!(reports[0]["metadata"], commit_report["metadata"]);
+    assert!(reports[0]["received-ms"].is_i64());
+    assert_eq!(reports[1]["metrics"], scan_report["metrics"]);
+    let response = client.get(format!("{}?limit=1", url)).dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["reports"].as_array().unwrap().len(), 1);
+
+    // reports must match the table they are sent to
+    let mut invalid = scan_report.clone();
+    invalid["snapshot-id"] = json!(8);
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(invalid.to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::BadRequest);
+    let mut invalid = commit_report.clone();
+    invalid["table-name"] = json!("rest.namespacenametest.othertable");
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(invalid.to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::BadRequest);
+    let response = client
+      .post(format!(
+        "/v1/namespaces/{}/tables/missing/metrics",
+        namespace_name
+      ))
+      .header(ContentType::JSON)
+      .body(commit_report.to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    // the reports follow a renamed table
+    let rename = |from: &str, to: &str| {
+      let request = json!({
+        "source": { "namespace": [namespace_name], "name": from },
+        "destination": { "namespace": [namespace_name], "name": to },
+      });
+      client
+        .post("/v1/tables/rename")
+        .header(ContentType::JSON)
+        .body(request.to_string())
+        .dispatch()
+    };
+    assert_eq!(
+      rename(table_name, "renamed").await.status(),
+      Status::NoContent
+    );
+    let response = client.get(&url).dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+    let renamed_url = format!("/v1/namespaces/{}/tables/renamed/metrics", namespace_name);
+    let response = client.get(&renamed_url).dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["reports"].as_array().unwrap().len(), 2);
+    assert_eq!(
+      rename("renamed", table_name).await.status(),
+      Status::NoContent
+    );
+
+    // the reports of a dropped table come back when it is restored
+    let response = client.delete(&table_url).dispatch().await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.get(&url).dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.get("/v1/admin/dropped").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    let restore_url = format!(
+      "/v1/admin/dropped/{}/restore",
+      body["dropped"][0]["id"].as_str().unwrap()
+    );
+    let response = client.post(&restore_url).dispatch().await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.get(&url).dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["reports"].as_array().unwrap().len(), 2);
+
+    // purging the table drops its reports
+    let response = client
+      .delete(format!("{}?purgeRequested=true", table_url))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.get(&url).dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+    // a new table under the same name starts without reports
+    let response = client
+      .post(format!("/v1/namespaces/{}/tables", namespace_name))
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&mock_create_table_request(table_name)).unwrap())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let response = client.get(&url).dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["reports"], json!([]));
+  }
 }
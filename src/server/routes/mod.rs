@@ -1,3 +1,4 @@
+pub mod admin;
 pub mod common;
 pub mod config;
 pub mod metric;
@@ -5,3 +6,4 @@ pub mod namespace;
 pub mod request;
 pub mod response;
 pub mod table;
+pub mod view;
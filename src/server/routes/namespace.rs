@@ -1,3 +1,4 @@
+use crate::catalog::metric::Metric;
 use crate::catalog::namespace::{Namespace, NamespaceIdent};
 use crate::common::result::{self, EmptyResult, ErrorType, JsonResult, Location, Result};
 use crate::{err, ok_empty, ok_json};
@@ -5,15 +6,15 @@ use std::collections::HashSet;
 
 use rocket::request::FromParam;
 
-use rocket::{
-  serde::{
-    json::{Json, Value},
-    Deserialize, Serialize,
-  },
-  State,
+use rocket::serde::{
+  json::{Json, Value},
+  Deserialize, Serialize,
 };
 
-use crate::db::DB;
+use crate::server::prefix::CatalogDB;
+use crate::server::routes::config::ServerConfig;
+use crate::server::routes::request::PageRequest;
+use rocket::State;
 
 pub struct NamespaceParam(pub Vec<NamespaceIdent>);
 
@@ -64,32 +65,37 @@ pub struct UpdateNamespaceRequest {
   pub updates: Option<Value>,
 }
 
-/// List namespaces, optionally providing a parent namespace to list underneath
-#[get("/namespaces?<parent..>")]
-pub async fn get(parent: Option<&str>, db: &State<DB>) -> JsonResult {
-  let conn = db.get_read_conn()?;
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+// Rename Namespace Request
+pub struct RenameNamespaceRequest {
+  pub source: Vec<NamespaceIdent>,
+  pub destination: Vec<NamespaceIdent>,
+}
+
+/// List namespaces, optionally providing a parent namespace to list underneath, a page at a time if
+/// requested
+#[get("/namespaces?<parent>&<page..>")]
+pub async fn get(parent: Option<&str>, page: PageRequest, db: CatalogDB<'_>) -> JsonResult {
+  let conn = db.conn();
   let parent = if let Some(p_str) = parent {
     NamespaceParam::try_from(p_str)?.0
   } else {
     vec![]
   };
-  let res = Namespace::list(&conn, &parent);
-  match res {
-    None => err!(
-      ErrorType::NotFound,
-      Location::Namespace,
-      format!("Namespace {} not found", parent.join("."))
-    ),
-    Some(v) => ok_json!( { "namespaces": v }),
+  let page = Namespace::list(conn, &parent, &page)?;
+  match page.next_page_token {
+    None => ok_json!({ "namespaces": page.items }),
+    Some(token) => ok_json!({ "namespaces": page.items, "next-page-token": token }),
   }
 }
 
 /// Create a namespace
 #[post("/", data = "<create_request>")]
-pub async fn post(create_request: Json<CreateNamespaceRequest>, db: &State<DB>) -> JsonResult {
-  let mut conn = db.get_write_conn()?;
+pub async fn post(create_request: Json<CreateNamespaceRequest>, db: CatalogDB<'_>) -> JsonResult {
+  let conn = db.conn();
   let created_namespace = Namespace::create(
-    &mut conn,
+    conn,
     &create_request.namespace,
     create_request.properties.clone(), // FIXME: this is a clone, can it be avoided?
   )?;
@@ -101,9 +107,9 @@ pub async fn post(create_request: Json<CreateNamespaceRequest>, db: &State<DB>)
 
 /// Check if a namespace exists
 #[head("/<namespace>")]
-pub async fn head_by_name(namespace: NamespaceParam, db: &State<DB>) -> EmptyResult {
-  let conn = db.get_read_conn()?;
-  let exists = Namespace::exists(&conn, &namespace.0);
+pub async fn head_by_name(namespace: NamespaceParam, db: CatalogDB<'_>) -> EmptyResult {
+  let conn = db.conn();
+  let exists = Namespace::exists(conn, &namespace.0)?;
   match exists {
     true => ok_empty!(),
     false => err!(
@@ -116,17 +122,45 @@ pub async fn head_by_name(namespace: NamespaceParam, db: &State<DB>) -> EmptyRes
 
 /// Load the metadata properties for a namespace
 #[get("/<namespace>")]
-pub async fn get_by_name(namespace: NamespaceParam, db: &State<DB>) -> JsonResult {
-  let conn = db.get_read_conn()?;
-  let properties = Namespace::get_properties(&conn, &namespace.0)?;
+pub async fn get_by_name(namespace: NamespaceParam, db: CatalogDB<'_>) -> JsonResult {
+  let conn = db.conn();
+  let properties = Namespace::get_properties(conn, &namespace.0)?;
   ok_json!({ "properties": properties })
 }
 
-/// Drop a namespace from the catalog. Namespace must be empty.
+/// Drop a namespace from the catalog. Namespace must be empty, it can be restored until its
+/// retention expires.
 #[delete("/<namespace>")]
-pub async fn delete_by_name(namespace: NamespaceParam, db: &State<DB>) -> EmptyResult {
-  let mut conn = db.get_write_conn()?;
-  Namespace::delete(&mut conn, &namespace.0)?;
+pub async fn delete_by_name(
+  namespace: NamespaceParam,
+  db: CatalogDB<'_>,
+  config: &State<ServerConfig>,
+) -> EmptyResult {
+  let conn = db.conn();
+  Namespace::delete(conn, &namespace.0, config.drop_retention_ms())?;
+  ok_empty!()
+}
+
+/// Rename a namespace, its tables and child namespaces move with it
+#[post("/rename", data = "<rename_request>")]
+pub async fn rename(
+  rename_request: Json<RenameNamespaceRequest>,
+  db: CatalogDB<'_>,
+) -> EmptyResult {
+  let conn = db.conn();
+  Namespace::rename(conn, &rename_request.source, &rename_request.destination)?;
+  // the namespace is renamed already, reports that could not be moved stay under the old name
+  if let Err(e) = Metric::rename_namespace(
+    db.metrics_conn(),
+    &rename_request.source,
+    &rename_request.destination,
+  ) {
+    eprintln!(
+      "moving the metrics reports of namespace {} failed: {}",
+      rename_request.source.join("."),
+      e.message
+    );
+  }
   ok_empty!()
 }
 
@@ -135,7 +169,7 @@ pub async fn delete_by_name(namespace: NamespaceParam, db: &State<DB>) -> EmptyR
 pub fn post_properties(
   namespace: NamespaceParam,
   mut update_request: Json<UpdateNamespaceRequest>,
-  db: &State<DB>,
+  db: CatalogDB<'_>,
 ) -> JsonResult {
   // we don't test the uniqueness of the keys in removals, it will be treated as a no-op.
   // we only test if a key is presented both in the removals and update.
@@ -164,9 +198,9 @@ pub fn post_properties(
     }
   }
 
-  let mut conn = db.get_write_conn()?;
+  let conn = db.conn();
   let res = Namespace::update(
-    &mut conn,
+    conn,
     &namespace.0,
     update_request.removals.take(),
     update_request.updates.take(),
@@ -184,7 +218,8 @@ pub fn stage() -> rocket::fairing::AdHoc {
           head_by_name,
           get_by_name,
           delete_by_name,
-          post_properties
+          post_properties,
+          rename
         ],
       )
       .mount("/v1", routes![get]) // for a query parameter
@@ -194,9 +229,13 @@ pub fn stage() -> rocket::fairing::AdHoc {
 #[cfg(test)]
 mod test {
   use super::*;
-  use crate::table::test::create_mock_client;
+  use crate::db::Backend;
+  use crate::table::test::{
+    create_mock_client, create_mock_client_with_backend, mock_create_table_request,
+  };
   use rocket::http::ContentType;
   use rocket::http::Status;
+  use rocket::serde::json::json;
 
   #[rocket::async_test]
   async fn test_list_non_exist_namespace() {
@@ -232,13 +271,51 @@ mod test {
     assert_eq!(response.status(), Status::NotFound); // TODO: FIXME: NotFound Or Ok?
   }
 
+  #[rocket::async_test]
+  async fn test_list_namespaces_paged() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    for name in ["c", "a", "b"] {
+      let response = client
+        .post("/v1/namespaces")
+        .header(ContentType::JSON)
+        .body(json!({ "namespace": [name] }).to_string())
+        .dispatch()
+        .await;
+      assert_eq!(response.status(), Status::Ok);
+    }
+
+    // without pagination the namespaces come in the same order as the pages
+    let response = client.get("/v1/namespaces").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["namespaces"], json!([["a"], ["b"], ["c"]]));
+    assert!(body.get("next-page-token").is_none());
+
+    let response = client.get("/v1/namespaces?pageSize=2").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["namespaces"], json!([["a"], ["b"]]));
+    let token = body["next-page-token"].as_str().unwrap();
+    let response = client
+      .get(format!("/v1/namespaces?pageToken={}&pageSize=2", token))
+      .dispatch()
+      .await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["namespaces"], json!([["c"]]));
+    assert!(body.get("next-page-token").is_none());
+
+    let response = client.get("/v1/namespaces?pageSize=0").dispatch().await;
+    assert_eq!(response.status(), Status::BadRequest);
+  }
+
   #[rocket::async_test]
   async fn test_create_namespace() {
     let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
     let client = create_mock_client(temp_dir.path().to_path_buf()).await;
 
     let namespace_name = "create_namespace";
-    let endpoint = format!("/v1/namespaces/");
+    let endpoint = "/v1/namespaces/".to_string();
 
     let create_namespace_request = CreateNamespaceRequest {
       namespace: vec![namespace_name.to_string()], // Use String directly
@@ -256,6 +333,16 @@ mod test {
       .await;
 
     assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+      .post("/v1/namespaces")
+      .header(ContentType::JSON)
+      .body(json!({ "namespace": ["listed"], "properties": ["owner"] }).to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::BadRequest);
+    let response = client.get("/v1/namespaces/listed").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
   }
 
   #[rocket::async_test]
@@ -277,7 +364,7 @@ mod test {
     let client = create_mock_client(temp_dir.path().to_path_buf()).await;
 
     let namespace_name = "exist";
-    let endpoint = format!("/v1/namespaces/");
+    let endpoint = "/v1/namespaces/".to_string();
 
     let create_namespace_request = CreateNamespaceRequest {
       namespace: vec![namespace_name.to_string()], // Use String directly
@@ -309,7 +396,7 @@ mod test {
     let client = create_mock_client(temp_dir.path().to_path_buf()).await;
 
     let namespace_name = "exist";
-    let endpoint = format!("/v1/namespaces/");
+    let endpoint = "/v1/namespaces/".to_string();
 
     let create_namespace_request = CreateNamespaceRequest {
       namespace: vec![namespace_name.to_string()], // Use String directly
@@ -354,7 +441,7 @@ mod test {
     let client = create_mock_client(temp_dir.path().to_path_buf()).await;
 
     let namespace_name = "exist";
-    let endpoint = format!("/v1/namespaces/");
+    let endpoint = "/v1/namespaces/".to_string();
 
     let create_namespace_request = CreateNamespaceRequest {
       namespace: vec![namespace_name.to_string()], // Use String directly
@@ -391,4 +478,275 @@ mod test {
 
     assert_eq!(response.status(), Status::NotFound);
   }
+
+  #[rocket::async_test]
+  async fn test_nested_namespaces() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let create = |levels: &[&str]| {
+      serde_json::to_vec(&CreateNamespaceRequest {
+        namespace: levels.iter().map(|x| x.to_string()).collect(),
+        properties: None,
+      })
+      .unwrap()
+    };
+
+    // the parent has to exist
+    let response = client
+      .post("/v1/namespaces")
+      .header(ContentType::JSON)
+      .body(create(&["parent", "child"]))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    for levels in [
+      vec!["parent"],
+      vec!["parent", "child"],
+      vec!["parent", "other"],
+    ] {
+      let response = client
+        .post("/v1/namespaces")
+        .header(ContentType::JSON)
+        .body(create(&levels))
+        .dispatch()
+        .await;
+      assert_eq!(response.status(), Status::Ok);
+    }
+
+    let response = client.get("/v1/namespaces").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["namespaces"], json!([["parent"]]));
+    let response = client.get("/v1/namespaces?parent=parent").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(
+      body["namespaces"],
+      json!([["parent", "child"], ["parent", "other"]])
+    );
+
+    // a namespace with children cannot be dropped
+    let response = client.delete("/v1/namespaces/parent").dispatch().await;
+    assert_eq!(response.status(), Status::BadRequest);
+
+    let response = client
+      .delete("/v1/namespaces/parent%1Fchild")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.get("/v1/namespaces?parent=parent").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["namespaces"], json!([["parent", "other"]]));
+
+    let response = client
+      .delete("/v1/namespaces/parent%1Fother")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.delete("/v1/namespaces/parent").dispatch().await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.get("/v1/namespaces").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["namespaces"], json!([]));
+  }
+
+  #[rocket::async_test]
+  async fn test_update_namespace_properties() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let create_namespace_request = CreateNamespaceRequest {
+      namespace: vec!["props".to_string()],
+      properties: Some(json!({ "owner": "alice", "team": "data" })),
+    };
+    let response = client
+      .post("/v1/namespaces")
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&create_namespace_request).unwrap())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let response = client
+      .post("/v1/namespaces/props/properties")
+      .header(ContentType::JSON)
+      .body(
+        json!({
+          "removals": ["team", "missing"],
+          "updates": { "owner": "bob", "location": "/tmp/props" },
+        })
+        .to_string(),
+      )
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["removed_keys"], json!(["team"]));
+    assert_eq!(body["missing_keys"], json!(["missing"]));
+
+    // the update is visible to the following requests
+    let response = client.get("/v1/namespaces/props").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let properties = response.into_json::<Value>().await.unwrap()["properties"].take();
+    assert_eq!(properties["owner"], "bob");
+    assert_eq!(properties["location"], "/tmp/props");
+    assert!(properties.get("team").is_none());
+    assert!(properties.get("created_at").is_some());
+
+    let response = client
+      .post("/v1/namespaces/props/properties")
+      .header(ContentType::JSON)
+      .body(json!({ "updates": ["owner"] }).to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::BadRequest);
+  }
+
+  #[rocket::async_test]
+  async fn test_rename_namespace() {
+    for backend in [Backend::Pickledb, Backend::Redb, Backend::Sqlite] {
+      let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+      let client =
+        create_mock_client_with_backend(temp_dir.path().to_path_buf(), Default::default(), backend)
+          .await;
+
+      for levels in [vec!["a"], vec!["a", "b"], vec!["a", "b", "c"], vec!["x"]] {
+        let request = CreateNamespaceRequest {
+          namespace: levels.iter().map(|x| x.to_string()).collect(),
+          properties: Some(json!({ "owner": levels.join(".") })),
+        };
+        let response = client
+          .post("/v1/namespaces")
+          .header(ContentType::JSON)
+          .body(serde_json::to_vec(&request).unwrap())
+          .dispatch()
+          .await;
+        assert_eq!(response.status(), Status::Ok);
+      }
+      for (namespace, table) in [("a", "t1"), ("a%1Fb%1Fc", "t3")] {
+        let response = client
+          .post(format!("/v1/namespaces/{}/tables", namespace))
+          .header(ContentType::JSON)
+          .body(serde_json::to_vec(&mock_create_table_request(table)).unwrap())
+          .dispatch()
+          .await;
+        assert_eq!(response.status(), Status::Ok);
+      }
+      let commit = json!({
+        "requirements": [],
+        "updates": [{
+          "action": "add-snapshot",
+          "snapshot": {
+            "snapshot-id": 7,
+            "timestamp-ms": 1700000000000i64,
+            "manifest-list": "/tmp/snap-7.avro",
+            "summary": { "operation": "append" },
+          },
+        }],
+      });
+      let response = client
+        .post("/v1/namespaces/a%1Fb%1Fc/tables/t3")
+        .header(ContentType::JSON)
+        .body(commit.to_string())
+        .dispatch()
+        .await;
+      assert_eq!(response.status(), Status::Ok);
+      let report = json!({
+        "report-type": "commit-report",
+        "table-name": "t3",
+        "snapshot-id": 7,
+        "sequence-number": 1,
+        "operation": "append",
+        "metrics": {},
+      });
+      let response = client
+        .post("/v1/namespaces/a%1Fb%1Fc/tables/t3/metrics")
+        .header(ContentType::JSON)
+        .body(report.to_string())
+        .dispatch()
+        .await;
+      assert_eq!(response.status(), Status::NoContent);
+      let rename = |source: &[&str], destination: &[&str]| {
+        client
+          .post("/v1/namespaces/rename")
+          .header(ContentType::JSON)
+          .body(json!({ "source": source, "destination": destination }).to_string())
+          .dispatch()
+      };
+
+      assert_eq!(rename(&["a"], &["x"]).await.status(), Status::Conflict);
+      assert_eq!(
+        rename(&["missing"], &["y"]).await.status(),
+        Status::NotFound
+      );
+      assert_eq!(rename(&["a"], &["z", "a"]).await.status(), Status::NotFound);
+      assert_eq!(
+        rename(&["a"], &["a", "b", "a"]).await.status(),
+        Status::BadRequest
+      );
+      assert_eq!(rename(&[], &["y"]).await.status(), Status::BadRequest);
+
+      let response = rename(&["a"], &["x", "y"]).await;
+      assert_eq!(response.status(), Status::NoContent);
+
+      let list = |parent: &str| {
+        let client = &client;
+        let url = match parent {
+          "" => "/v1/namespaces".to_string(),
+          parent => format!("/v1/namespaces?parent={}", parent),
+        };
+        async move {
+          let body = client.get(url).dispatch().await.into_json::<Value>().await;
+          body.unwrap()["namespaces"].take()
+        }
+      };
+      assert_eq!(list("").await, json!([["x"]]));
+      assert_eq!(list("x").await, json!([["x", "y"]]));
+      assert_eq!(list("x%1Fy").await, json!([["x", "y", "b"]]));
+      assert_eq!(list("x%1Fy%1Fb").await, json!([["x", "y", "b", "c"]]));
+      let response = client.get("/v1/namespaces/a").dispatch().await;
+      assert_eq!(response.status(), Status::NotFound);
+      let response = client.get("/v1/namespaces/x%1Fy%1Fb%1Fc").dispatch().await;
+      let properties = response.into_json::<Value>().await.unwrap()["properties"].take();
+      assert_eq!(properties["owner"], "a.b.c");
+
+      // the tables moved with their namespaces
+      for (namespace, table) in [("x%1Fy", "t1"), ("x%1Fy%1Fb%1Fc", "t3")] {
+        let url = format!("/v1/namespaces/{}/tables/{}", namespace, table);
+        let response = client.get(url).dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+      }
+      let response = client.get("/v1/namespaces/a/tables/t1").dispatch().await;
+      assert_eq!(response.status(), Status::NotFound);
+      // and so did the metrics reports of their tables
+      let reports = |url: &'static str| {
+        let client = &client;
+        async move {
+          let body = client.get(url).dispatch().await.into_json::<Value>().await;
+          body.unwrap()["reports"].as_array().unwrap().len()
+        }
+      };
+      assert_eq!(
+        reports("/v1/namespaces/x%1Fy%1Fb%1Fc/tables/t3/metrics").await,
+        1
+      );
+      let response = client
+        .get("/v1/namespaces/a%1Fb%1Fc/tables/t3/metrics")
+        .dispatch()
+        .await;
+      assert_eq!(response.status(), Status::NotFound);
+      let response = client.get("/v1/namespaces/x%1Fy/tables").dispatch().await;
+      let body = response.into_json::<Value>().await.unwrap();
+      assert_eq!(
+        body["identifiers"],
+        json!([{ "namespace": ["x", "y"], "name": "t1" }])
+      );
+
+      // a rename under the same parent
+      let response = rename(&["x", "y"], &["x", "z"]).await;
+      assert_eq!(response.status(), Status::NoContent);
+      assert_eq!(list("x").await, json!([["x", "z"]]));
+    }
+  }
 }
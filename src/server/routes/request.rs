@@ -1,87 +1,425 @@
 use crate::server::routes::common::*;
+use rocket::serde::json::Value;
 use rocket::serde::Deserialize;
 use rocket::serde::Serialize;
+use std::collections::HashMap;
 
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
-// #[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub struct CreateTableRequest {
   pub name: String,
-  // location: Option<String>,
-  // schema: Schema,
-  // #[serde(rename = "partition-spec")]
-  // partition_spec: PartitionSpec,
-  // #[serde(rename = "write-order")]
-  // write_order: SortOrder,
-  // #[serde(rename = "stage-create")]
-  // stage_create: bool,
-  // properties: Option<HashMap<String, String>>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub location: Option<String>,
+  pub schema: Schema,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub partition_spec: Option<PartitionSpec>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub write_order: Option<SortOrder>,
+  #[serde(default)]
+  pub stage_create: bool,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub properties: Option<HashMap<String, String>>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
-#[allow(dead_code)]
 pub struct RegisterTableRequest {
-  name: String,
+  pub name: String,
   #[serde(rename = "metadata-location")]
-  metadata_location: String, // TODO: need to modify to table schema
+  pub metadata_location: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
 pub struct CommitTableRequest {
-  // pub identifier: TableIdentifier,
+  // required in a multi-table transaction, the table is in the path of a single commit
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub identifier: Option<TableIdentifier>,
   pub requirements: Vec<TableRequirement>,
   pub updates: Vec<TableUpdate>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct CommitTransactionRequest {
+  pub table_changes: Vec<CommitTableRequest>,
+}
+
+#[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
-#[serde(rename_all = "camelCase")]
 pub struct TableRequirement {
   #[serde(flatten)]
   pub requirement: RequirementType,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
-#[serde(untagged)]
+#[serde(tag = "type")]
 pub enum RequirementType {
-  // AssertCreate(AssertCreate),
-  // AssertTableUUID(AssertTableUUID),
-  // AssertRefSnapshotId(AssertRefSnapshotId),
-  // AssertLastAssignedFieldId(AssertLastAssignedFieldId),
-  // AssertCurrentSchemaId(AssertCurrentSchemaId),
-  // AssertLastAssignedPartitionId(AssertLastAssignedPartitionId),
-  // AssertDefaultSpecId(AssertDefaultSpecId),
-  // AssertDefaultSortOrderId(AssertDefaultSortOrderId),
+  #[serde(rename = "assert-create")]
+  AssertCreate,
+  #[serde(rename = "assert-table-uuid")]
+  AssertTableUUID(AssertTableUUID),
+  #[serde(rename = "assert-ref-snapshot-id")]
+  AssertRefSnapshotId(AssertRefSnapshotId),
+  #[serde(rename = "assert-last-assigned-field-id")]
+  AssertLastAssignedFieldId(AssertLastAssignedFieldId),
+  #[serde(rename = "assert-current-schema-id")]
+  AssertCurrentSchemaId(AssertCurrentSchemaId),
+  #[serde(rename = "assert-last-assigned-partition-id")]
+  AssertLastAssignedPartitionId(AssertLastAssignedPartitionId),
+  #[serde(rename = "assert-default-spec-id")]
+  AssertDefaultSpecId(AssertDefaultSpecId),
+  #[serde(rename = "assert-default-sort-order-id")]
+  AssertDefaultSortOrderId(AssertDefaultSortOrderId),
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(crate = "rocket::serde")]
-#[serde(untagged)]
+pub struct AssertTableUUID {
+  pub uuid: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct AssertRefSnapshotId {
+  #[serde(rename = "ref")]
+  pub ref_: String,
+  // a missing snapshot id means the ref must not exist yet
+  #[serde(default)]
+  pub snapshot_id: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct AssertLastAssignedFieldId {
+  pub last_assigned_field_id: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct AssertCurrentSchemaId {
+  pub current_schema_id: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct AssertLastAssignedPartitionId {
+  pub last_assigned_partition_id: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct AssertDefaultSpecId {
+  pub default_spec_id: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct AssertDefaultSortOrderId {
+  pub default_sort_order_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(tag = "action")]
 pub enum TableUpdate {
-  // AssignUUIDUpdate(AssignUUIDUpdate),
-  // UpgradeFormatVersionUpdate(UpgradeFormatVersionUpdate),
-  // AddSchemaUpdate(AddSchemaUpdate),
-  // SetCurrentSchemaUpdate(SetCurrentSchemaUpdate),
-  // AddPartitionSpecUpdate(AddPartitionSpecUpdate),
-  // SetDefaultSpecUpdate(SetDefaultSpecUpdate),
-  // AddSortOrderUpdate(AddSortOrderUpdate),
-  // SetDefaultSortOrderUpdate(SetDefaultSortOrderUpdate),
-  // AddSnapshotUpdate(AddSnapshotUpdate),
-  // SetSnapshotRefUpdate(SetSnapshotRefUpdate),
-  // RemoveSnapshotsUpdate(RemoveSnapshotsUpdate),
-  // RemoveSnapshotRefUpdate(RemoveSnapshotRefUpdate),
-  // SetLocationUpdate(SetLocationUpdate),
-  // SetPropertiesUpdate(SetPropertiesUpdate),
-  // RemovePropertiesUpdate(RemovePropertiesUpdate),
+  #[serde(rename = "assign-uuid")]
+  AssignUUID(AssignUUIDUpdate),
+  #[serde(rename = "upgrade-format-version")]
+  UpgradeFormatVersion(UpgradeFormatVersionUpdate),
+  #[serde(rename = "add-schema")]
+  AddSchema(AddSchemaUpdate),
+  #[serde(rename = "set-current-schema")]
+  SetCurrentSchema(SetCurrentSchemaUpdate),
+  #[serde(rename = "add-spec")]
+  AddPartitionSpec(AddPartitionSpecUpdate),
+  #[serde(rename = "set-default-spec")]
+  SetDefaultSpec(SetDefaultSpecUpdate),
+  #[serde(rename = "add-sort-order")]
+  AddSortOrder(AddSortOrderUpdate),
+  #[serde(rename = "set-default-sort-order")]
+  SetDefaultSortOrder(SetDefaultSortOrderUpdate),
+  #[serde(rename = "add-snapshot")]
+  AddSnapshot(AddSnapshotUpdate),
+  #[serde(rename = "set-snapshot-ref")]
+  SetSnapshotRef(SetSnapshotRefUpdate),
+  #[serde(rename = "remove-snapshots")]
+  RemoveSnapshots(RemoveSnapshotsUpdate),
+  #[serde(rename = "remove-snapshot-ref")]
+  RemoveSnapshotRef(RemoveSnapshotRefUpdate),
+  #[serde(rename = "set-location")]
+  SetLocation(SetLocationUpdate),
+  #[serde(rename = "set-properties")]
+  SetProperties(SetPropertiesUpdate),
+  #[serde(rename = "remove-properties")]
+  RemoveProperties(RemovePropertiesUpdate),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct AssignUUIDUpdate {
+  pub uuid: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct UpgradeFormatVersionUpdate {
+  pub format_version: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct AddSchemaUpdate {
+  pub schema: Schema,
+  // computed on the server side when omitted
+  #[serde(default)]
+  pub last_column_id: Option<i32>,
+}
+
+// an id of -1 in the set-* updates refers to the last one added in the same commit
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct SetCurrentSchemaUpdate {
+  pub schema_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct AddPartitionSpecUpdate {
+  pub spec: PartitionSpec,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct SetDefaultSpecUpdate {
+  pub spec_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct AddSortOrderUpdate {
+  pub sort_order: SortOrder,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct SetDefaultSortOrderUpdate {
+  pub sort_order_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct AddSnapshotUpdate {
+  pub snapshot: Snapshot,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct SetSnapshotRefUpdate {
+  pub ref_name: String,
+  #[serde(flatten)]
+  pub reference: SnapshotReference,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct RemoveSnapshotsUpdate {
+  pub snapshot_ids: Vec<i64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct RemoveSnapshotRefUpdate {
+  pub ref_name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct SetLocationUpdate {
+  pub location: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct SetPropertiesUpdate {
+  pub updates: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct RemovePropertiesUpdate {
+  pub removals: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct CreateViewRequest {
+  pub name: String,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub location: Option<String>,
+  pub schema: Schema,
+  pub view_version: ViewVersion,
+  #[serde(default)]
+  pub properties: HashMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CommitViewRequest {
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub identifier: Option<TableIdentifier>,
+  #[serde(default)]
+  pub requirements: Vec<ViewRequirement>,
+  pub updates: Vec<ViewUpdate>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+#[serde(tag = "type")]
+pub enum ViewRequirement {
+  #[serde(rename = "assert-view-uuid")]
+  AssertViewUUID(AssertViewUUID),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct AssertViewUUID {
+  pub uuid: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(tag = "action")]
+pub enum ViewUpdate {
+  #[serde(rename = "assign-uuid")]
+  AssignUUID(AssignUUIDUpdate),
+  #[serde(rename = "upgrade-format-version")]
+  UpgradeFormatVersion(UpgradeFormatVersionUpdate),
+  #[serde(rename = "add-schema")]
+  AddSchema(AddSchemaUpdate),
+  #[serde(rename = "set-location")]
+  SetLocation(SetLocationUpdate),
+  #[serde(rename = "set-properties")]
+  SetProperties(SetPropertiesUpdate),
+  #[serde(rename = "remove-properties")]
+  RemoveProperties(RemovePropertiesUpdate),
+  #[serde(rename = "add-view-version")]
+  AddViewVersion(AddViewVersionUpdate),
+  #[serde(rename = "set-current-view-version")]
+  SetCurrentViewVersion(SetCurrentViewVersionUpdate),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct AddViewVersionUpdate {
+  pub view_version: ViewVersion,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct SetCurrentViewVersionUpdate {
+  pub view_version_id: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(tag = "report-type")]
+pub enum ReportMetricsRequest {
+  #[serde(rename = "scan-report")]
+  Scan(ScanReport),
+  #[serde(rename = "commit-report")]
+  Commit(CommitReport),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct ScanReport {
+  pub table_name: String,
+  pub snapshot_id: i64,
+  // an Iceberg expression, stored as is
+  pub filter: Value,
+  pub schema_id: i32,
+  pub projected_field_ids: Vec<i32>,
+  pub projected_field_names: Vec<String>,
+  pub metrics: Metrics,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub metadata: Option<HashMap<String, String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct CommitReport {
+  pub table_name: String,
+  pub snapshot_id: i64,
+  pub sequence_number: i64,
+  pub operation: String,
+  pub metrics: Metrics,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub metadata: Option<HashMap<String, String>>,
+}
+
+pub type Metrics = HashMap<String, MetricResult>;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(untagged)]
+pub enum MetricResult {
+  Counter(CounterResult),
+  Timer(TimerResult),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+pub struct CounterResult {
+  pub unit: String,
+  pub value: i64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(crate = "rocket::serde")]
+#[serde(rename_all = "kebab-case")]
+pub struct TimerResult {
+  pub time_unit: String,
+  pub count: i64,
+  pub total_duration: i64,
+}
+
+// Pagination of list requests, an empty `pageToken` asks for the first page.
+#[derive(FromForm, Default)]
+pub struct PageRequest {
+  #[field(name = "pageToken")]
+  pub page_token: Option<String>,
+  #[field(name = "pageSize")]
+  pub page_size: Option<usize>,
 }
 
 #[derive(FromForm)]
-#[allow(dead_code)]
 pub struct PurgeRequested {
-  purge_requested: Option<bool>,
+  #[field(name = "purgeRequested")]
+  pub purge_requested: Option<bool>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -1,11 +1,42 @@
+use crate::catalog::dropped::Dropped;
+use crate::catalog::metric::StoredReport;
 use crate::server::routes::common::*;
-use rocket::serde::Serialize;
+use rocket::serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// #[get("/config")] --> 200: CatalogConfig
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(crate = "rocket::serde")]
+pub struct CatalogConfig {
+  // properties used as defaults, the client configuration takes precedence over them
+  #[serde(default)]
+  pub defaults: HashMap<String, String>,
+  // properties that take precedence over the client configuration
+  #[serde(default)]
+  pub overrides: HashMap<String, String>,
+}
+
+// #[get("/namespaces/<namespace>/tables/<table>/metrics")] --> 200: ListMetricsResponse
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ListMetricsResponse {
+  pub reports: Vec<StoredReport>,
+}
+
+// #[get("/admin/dropped")] --> 200: ListDroppedResponse
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ListDroppedResponse {
+  pub dropped: Vec<Dropped>,
+}
 
 // #[get("/namespaces/<namespace>/tables")] --> 200: ListTablesResponse
 #[derive(Serialize)]
 #[serde(crate = "rocket::serde")]
 pub struct ListTablesResponse {
   pub identifiers: Vec<TableIdentifier>,
+  #[serde(rename = "next-page-token", skip_serializing_if = "Option::is_none")]
+  pub next_page_token: Option<String>,
 }
 
 // #[post("/namespaces/<namespace>/tables")] --> 200: CreateTableResponse
@@ -17,7 +48,8 @@ pub type LoadTableResponse = LoadTableResult;
 #[derive(Serialize)]
 #[serde(crate = "rocket::serde")]
 pub struct LoadTableResult {
-  // pub metadata_location: Option<String>,
+  #[serde(rename = "metadata-location", skip_serializing_if = "Option::is_none")]
+  pub metadata_location: Option<String>,
   pub metadata: TableMetadata,
   // pub config: HashMap<String, String>,
 }
@@ -25,10 +57,22 @@ pub struct LoadTableResult {
 #[derive(Serialize)]
 #[serde(crate = "rocket::serde")]
 pub struct CommitTableResponse {
+  #[serde(rename = "metadata-location")]
   pub metadata_location: String,
   pub metadata: TableMetadata,
 }
 
+// #[post("/namespaces/<namespace>/views")] --> 200: LoadViewResult
+// #[get("/namespaces/<namespace>/views/<view>")] --> 200: LoadViewResult
+// #[post("/namespaces/<namespace>/views/<view>")] --> 200: LoadViewResult
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LoadViewResult {
+  #[serde(rename = "metadata-location", skip_serializing_if = "Option::is_none")]
+  pub metadata_location: Option<String>,
+  pub metadata: ViewMetadata,
+}
+
 // 400: BadRequestErrorResponse
 // 404: IcebergErrorResponse
 // 409: TableAlreadyExistsError
@@ -36,12 +80,14 @@ pub struct CommitTableResponse {
 // 5XX: ServerErrorResponse
 #[derive(Serialize)]
 #[serde(crate = "rocket::serde")]
+#[allow(dead_code)]
 pub struct IcebergErrorResponse {
   pub error: ErrorModel,
 }
 
 #[derive(Serialize)]
 #[serde(crate = "rocket::serde")]
+#[allow(dead_code)]
 pub struct ErrorModel {
   pub message: String,
   pub r#type: String, // Using r#type to avoid conflict with the type keyword
@@ -3,37 +3,29 @@ use crate::server::routes::common::*;
 use crate::{err, ok_empty, response::*};
 use rocket::serde::json::Json;
 
-use crate::catalog::table::Table;
+use crate::catalog::{metric::Metric, purge::Purge, table::Table};
 use crate::common::result::{EmptyResult, ErrorType, Location, Result};
+use crate::server::prefix::{CatalogDB, CatalogWarehouse};
+use crate::server::routes::config::ServerConfig;
 use crate::server::routes::namespace::NamespaceParam;
 
-use crate::DB;
 use rocket::State;
 
 pub type JsonResultGeneric<T> = Result<Json<T>>;
 
-fn hash<'a>(level: &Vec<String>) -> String {
-  if level.is_empty() {
-    "root".to_string()
-  } else {
-    format!("root::{}", level.join("::"))
-  }
-}
-
-/// List all table identifiers underneath a given namespace
-#[get("/namespaces/<namespace>/tables")]
+/// List all table identifiers underneath a given namespace, a page at a time if requested
+#[get("/namespaces/<namespace>/tables?<page..>")]
 pub fn get_table_by_namespace(
   namespace: NamespaceParam,
-  db: &State<DB>,
+  page: PageRequest,
+  db: CatalogDB<'_>,
 ) -> JsonResultGeneric<ListTablesResponse> {
-  let mut conn = db.get_read_conn()?;
+  let conn = db.conn();
   let copy = namespace.0.clone();
-  let hash_key = hash(&namespace.0);
-  let table_names = Table::list(&mut conn, hash_key.to_string());
-  let all_table_names = table_names.clone();
+  let table_names = Table::list(conn, &namespace.0, &page)?;
 
   let mut identifiers = Vec::new();
-  for table_name in all_table_names.into_iter().flatten() {
+  for table_name in table_names.items {
     let identifier = TableIdentifier {
       namespace: NamespaceResponse(copy.clone()), // Assuming namespace is a Vec<String>
       name: table_name.clone(),
@@ -41,16 +33,11 @@ pub fn get_table_by_namespace(
     identifiers.push(identifier);
   }
 
-  if identifiers.is_empty() {
-    return err!(
-      ErrorType::NotFound,
-      Location::Table,
-      format!("No tables found for the specified namespace")
-    );
-  }
-
   // Create and return ListTablesResponse
-  let response = ListTablesResponse { identifiers };
+  let response = ListTablesResponse {
+    identifiers,
+    next_page_token: table_names.next_page_token,
+  };
 
   Ok(Json(response))
 }
@@ -61,27 +48,34 @@ pub fn get_table_by_namespace(
 pub fn post_table_by_namespace(
   namespace: NamespaceParam,
   create_table_request: Json<CreateTableRequest>,
-  db: &State<DB>,
-  table_metedata_generator: &State<TableMetadataAtomicIncr>,
+  db: CatalogDB<'_>,
+  table_metedata_generator: &State<TableMetadataGenerator>,
+  warehouse: CatalogWarehouse<'_>,
 ) -> JsonResultGeneric<CreateTableResponse> {
-  let mut conn = db.get_write_conn()?;
-  let hash_key = hash(&namespace.0);
-  let new_table = Table::create(
-    &mut conn,
-    hash_key.to_string(),
-    create_table_request.name.clone().to_string(),
-    table_metedata_generator,
-  )?;
-
-  // Generate metadata for the newly created table
-  let metadata = TableMetadata {
-    format_version: new_table.metadata.format_version,
-    table_uuid: new_table.metadata.table_uuid,
-    // Fill in other fields as needed
+  let conn = db.conn();
+  let new_table = if create_table_request.stage_create {
+    Table::stage(
+      conn,
+      &namespace.0,
+      &create_table_request,
+      table_metedata_generator,
+      &warehouse,
+    )?
+  } else {
+    Table::create(
+      conn,
+      &namespace.0,
+      &create_table_request,
+      table_metedata_generator,
+      &warehouse,
+    )?
   };
 
   // Construct the response
-  let response = CreateTableResponse { metadata };
+  let response = CreateTableResponse {
+    metadata_location: new_table.metadata_location,
+    metadata: new_table.metadata,
+  };
 
   // Return the response as JSON
   Ok(Json(response))
@@ -90,18 +84,19 @@ pub fn post_table_by_namespace(
 /// Register a table in the given namespace using given metadata file location
 #[post("/namespaces/<namespace>/register", data = "<register_table_request>")]
 pub fn register_table(
-  namespace: &str,
+  namespace: NamespaceParam,
   register_table_request: Json<RegisterTableRequest>,
+  db: CatalogDB<'_>,
 ) -> JsonResultGeneric<LoadTableResponse> {
-  // Generate metadata for the newly created table
-  let metadata = TableMetadata {
-    format_version: 1,
-    table_uuid: "generated_uuid".to_string(),
-    // Fill in other fields as needed
-  };
+  let conn = db.conn();
+  let request = register_table_request.into_inner();
+  let table = Table::register(conn, &namespace.0, request.name, request.metadata_location)?;
 
   // Construct the response
-  let response = LoadTableResponse { metadata };
+  let response = LoadTableResponse {
+    metadata_location: table.metadata_location,
+    metadata: table.metadata,
+  };
 
   // Return the response as JSON
   Ok(Json(response))
@@ -112,27 +107,31 @@ pub fn register_table(
 pub fn get_table(
   namespace: NamespaceParam,
   table: &str,
-  db: &State<DB>,
+  db: CatalogDB<'_>,
 ) -> JsonResultGeneric<LoadTableResponse> {
-  let conn = db.get_read_conn()?;
-  let hash_key = hash(&namespace.0);
+  let conn = db.conn();
   let table_data_option = Table::get(
-    &conn,
-    hash_key.to_string(),
+    conn,
+    &namespace.0,
     table.to_string(), // FIXME: this is a clone, can it be avoided?
-  );
-
-  // TODO: update to real metadata
-  let table_data = table_data_option.unwrap();
-  // Generate metadata for the newly created table
-  let metadata = TableMetadata {
-    format_version: table_data.metadata.format_version,
-    table_uuid: table_data.metadata.table_uuid,
-    // Fill in other fields as needed
+  )?;
+
+  let table_data = match table_data_option {
+    Some(table_data) => table_data,
+    None => {
+      return err!(
+        ErrorType::NotFound,
+        Location::Table,
+        format!("Table {} not found", table)
+      )
+    }
   };
 
   // Construct the response
-  let response = LoadTableResponse { metadata };
+  let response = LoadTableResponse {
+    metadata_location: table_data.metadata_location,
+    metadata: table_data.metadata,
+  };
 
   // Return the response as JSON
   Ok(Json(response))
@@ -144,48 +143,106 @@ pub fn get_table(
   data = "<commit_table_request>"
 )]
 pub fn post_table(
-  namespace: &str,
+  namespace: NamespaceParam,
   table: &str,
   commit_table_request: Json<CommitTableRequest>,
+  db: CatalogDB<'_>,
+  table_metedata_generator: &State<TableMetadataGenerator>,
+  warehouse: CatalogWarehouse<'_>,
 ) -> JsonResultGeneric<CommitTableResponse> {
-  // TODO: need to update metadata
-  // Generate metadata for the newly created table
-  let metadata = TableMetadata {
-    format_version: 1,
-    table_uuid: "generated_uuid".to_string(),
-    // Fill in other fields as needed
-  };
+  let conn = db.conn();
+  let commit_table_request = commit_table_request.into_inner();
+  let table_data = Table::commit(
+    conn,
+    &namespace.0,
+    table.to_string(),
+    &commit_table_request.requirements,
+    commit_table_request.updates,
+    table_metedata_generator,
+    &warehouse,
+  )?;
 
   // Construct the response
   let response = CommitTableResponse {
-    metadata,
-    metadata_location: "".to_string(),
+    metadata: table_data.metadata,
+    // tables outside of the local file system have no metadata file
+    metadata_location: table_data.metadata_location.unwrap_or_default(),
   };
 
   // Return the response as JSON
   Ok(Json(response))
 }
 
-/// Drop a table from the catalog
+/// Commit updates to several tables atomically
+#[post("/transactions/commit", data = "<commit_transaction_request>")]
+pub fn commit_transaction(
+  commit_transaction_request: Json<CommitTransactionRequest>,
+  db: CatalogDB<'_>,
+  table_metedata_generator: &State<TableMetadataGenerator>,
+  warehouse: CatalogWarehouse<'_>,
+) -> EmptyResult {
+  let conn = db.conn();
+  Table::commit_transaction(
+    conn,
+    commit_transaction_request.into_inner().table_changes,
+    table_metedata_generator,
+    &warehouse,
+  )?;
+  ok_empty!()
+}
+
+/// Drop a table from the catalog, and delete its files in the background if a purge is requested.
+/// Otherwise the table can be restored until its retention expires.
 #[delete("/namespaces/<namespace>/tables/<table>?<purge_requested..>")]
 pub fn delete_table(
   namespace: NamespaceParam,
   table: &str,
   purge_requested: PurgeRequested,
-  db: &State<DB>,
+  db: CatalogDB<'_>,
+  warehouse: CatalogWarehouse<'_>,
+  config: &State<ServerConfig>,
 ) -> EmptyResult {
-  let mut conn = db.get_write_conn()?;
-  let hash_key = hash(&namespace.0);
-  Table::delete(&mut conn, hash_key.to_string(), table.to_string())?;
+  let conn = db.conn();
+  let purge = purge_requested.purge_requested.unwrap_or(false);
+  let retention_ms = config.drop_retention_ms();
+  let dropped = Table::delete(
+    conn,
+    &namespace.0,
+    table.to_string(),
+    purge,
+    retention_ms,
+    &warehouse,
+  )?;
+  // reports of a dropped table should not show up for a new table with the same name
+  let metrics_conn = db.metrics_conn();
+  match dropped {
+    Some(dropped) => Metric::retain_dropped(metrics_conn, &namespace.0, table, &dropped.id)?,
+    None => {
+      Purge::start(db.shared_conn(), &namespace.0, table);
+      Metric::delete(metrics_conn, &namespace.0, table.to_string())?;
+    }
+  }
   ok_empty!()
 }
 
+/// Get the status of the purge of the files of the last table dropped under this name
+#[get("/namespaces/<namespace>/tables/<table>/purge")]
+pub fn get_purge(namespace: NamespaceParam, table: &str, db: CatalogDB<'_>) -> Result<Json<Purge>> {
+  match Purge::get(db.conn(), &namespace.0, table)? {
+    Some(purge) => Ok(Json(purge)),
+    None => err!(
+      ErrorType::NotFound,
+      Location::Table,
+      format!("No purge of table {} found", table)
+    ),
+  }
+}
+
 /// Check if a table exists
 #[head("/namespaces/<namespace>/tables/<table>")]
-pub fn head_table(namespace: NamespaceParam, table: &str, db: &State<DB>) -> EmptyResult {
-  let conn = db.get_read_conn()?;
-  let hash_key = hash(&namespace.0);
-  let exists = Table::exists(&conn, hash_key.to_string(), table.to_string());
+pub fn head_table(namespace: NamespaceParam, table: &str, db: CatalogDB<'_>) -> EmptyResult {
+  let conn = db.conn();
+  let exists = Table::exists(conn, &namespace.0, table.to_string())?;
 
   // let error = false;
   match exists {
@@ -194,43 +251,68 @@ pub fn head_table(namespace: NamespaceParam, table: &str, db: &State<DB>) -> Emp
     false => err!(
       ErrorType::NotFound,
       Location::Table,
-      format!("Table not found")
+      "Table not found".to_string()
     ),
   }
 }
 
 /// Rename a table from its current name to a new name
 #[post("/tables/rename", data = "<rename_table_request>")]
-pub fn rename_table(rename_table_request: Json<RenameTableRequest>, db: &State<DB>) -> EmptyResult {
-  let mut conn = db.get_write_conn()?;
-  // Table::rename(&mut conn, namespace.to_string(), table.to_string())?;
-  let tmp = &rename_table_request.source.namespace.0;
-  let namespace_hash = hash(&tmp);
-
-  // Table::rename(&mut conn, "a".to_string(), rename_table_request.source.name.clone(), rename_table_request.destination.name.clone());
+pub fn rename_table(
+  rename_table_request: Json<RenameTableRequest>,
+  db: CatalogDB<'_>,
+) -> EmptyResult {
+  let conn = db.conn();
+  let RenameTableRequest {
+    source,
+    destination,
+  } = rename_table_request.into_inner();
   Table::rename(
-    &mut conn,
-    namespace_hash,
-    rename_table_request.source.name.clone(),
-    rename_table_request.destination.name.clone(),
+    conn,
+    &source.namespace.0,
+    source.name.clone(),
+    &destination.namespace.0,
+    destination.name.clone(),
   )?;
+  // the table is renamed already, reports that could not be moved stay under the old name
+  if let Err(e) = Metric::rename(
+    db.metrics_conn(),
+    &source.namespace.0,
+    &source.name,
+    &destination.namespace.0,
+    &destination.name,
+  ) {
+    eprintln!(
+      "moving the metrics reports of table {} failed: {}",
+      source.name, e.message
+    );
+  }
   ok_empty!()
 }
 
 #[cfg(test)]
 pub(crate) mod test {
   use super::*;
+  use crate::admin;
+  use crate::catalog::avro::test::write_records;
+  use crate::catalog::file_io::Warehouse;
   use crate::config;
+  use crate::db::{Backend, Key};
   use crate::metric;
   use crate::namespace;
   use crate::server::catches;
+  use crate::server::prefix::{self, Catalog, Catalogs};
   use crate::server::routes::namespace::CreateNamespaceRequest;
   use crate::table;
+  use crate::view;
+  use crate::DB;
+  use apache_avro::{Codec, DeflateSettings};
   use rocket::http::ContentType;
   use rocket::http::Status;
   use rocket::local::asynchronous::Client;
+  use rocket::serde::json::{json, Value};
 
-  use std::path::PathBuf;
+  use std::path::{Path, PathBuf};
 
   #[rocket::async_test]
   async fn test_get_table_by_namespace_empty_result() {
@@ -244,6 +326,60 @@ pub(crate) mod test {
     assert_eq!(response.status(), Status::NotFound);
   }
 
+  #[rocket::async_test]
+  async fn test_get_table_by_namespace_paged() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    create_namespace_and_table(&client, "a", "t4").await;
+    for name in ["t2", "t0", "t3", "t1"] {
+      let response = client
+        .post("/v1/namespaces/a/tables")
+        .header(ContentType::JSON)
+        .body(serde_json::to_vec(&mock_create_table_request(name)).unwrap())
+        .dispatch()
+        .await;
+      assert_eq!(response.status(), Status::Ok);
+    }
+
+    let mut names = vec![];
+    let mut url = "/v1/namespaces/a/tables?pageToken=&pageSize=2".to_string();
+    loop {
+      let response = client.get(&url).dispatch().await;
+      assert_eq!(response.status(), Status::Ok);
+      let body = response.into_json::<Value>().await.unwrap();
+      let page = body["identifiers"].as_array().unwrap();
+      assert!(page.len() <= 2);
+      names.extend(page.iter().map(|x| x["name"].as_str().unwrap().to_string()));
+      match body.get("next-page-token") {
+        Some(token) => {
+          url = format!(
+            "/v1/namespaces/a/tables?pageToken={}&pageSize=2",
+            token.as_str().unwrap()
+          );
+          // tables dropped behind the token do not shift the next pages
+          if names.len() == 2 {
+            let response = client.delete("/v1/namespaces/a/tables/t0").dispatch().await;
+            assert_eq!(response.status(), Status::NoContent);
+          }
+        }
+        None => break,
+      }
+    }
+    assert_eq!(names, vec!["t0", "t1", "t2", "t3", "t4"]);
+
+    let response = client
+      .get("/v1/namespaces/a/tables?pageToken=xyz")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::BadRequest);
+    let response = client
+      .get("/v1/namespaces/missing/tables?pageToken=")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NotFound);
+  }
+
   #[rocket::async_test]
   async fn test_get_table_by_namespace_result_found() {
     let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
@@ -267,13 +403,14 @@ pub(crate) mod test {
 
     assert_eq!(response.status(), Status::Ok);
 
+    // an existing namespace without tables lists none
     let url = format!("/v1/namespaces/{}/tables", namespace_name);
     let get_response = client.get(&url).dispatch().await;
-    assert_eq!(get_response.status(), Status::NotFound);
+    assert_eq!(get_response.status(), Status::Ok);
+    let body = get_response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["identifiers"], json!([]));
 
-    let create_table_request = CreateTableRequest {
-      name: "tablenametest".to_string(),
-    };
+    let create_table_request = mock_create_table_request("tablenametest");
     let create_table_request_json = Json(create_table_request);
     let json_bytes = serde_json::to_vec(&create_table_request_json.into_inner()).unwrap();
 
@@ -347,6 +484,239 @@ pub(crate) mod test {
     assert_eq!(second_response.status(), Status::Conflict);
   }
 
+  #[rocket::async_test]
+  async fn test_post_table_by_namespace_assigns_fresh_ids() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let namespace_name = "testnamespacename";
+    let create_namespace_request = CreateNamespaceRequest {
+      namespace: vec![namespace_name.to_string()],
+      properties: None,
+    };
+    let response = client
+      .post("/v1/namespaces")
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&create_namespace_request).unwrap())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let warehouse = std::fs::canonicalize(temp_dir.path().join("warehouse")).unwrap();
+    let location = warehouse.join("tablenametest");
+    let location = location.to_str().unwrap();
+    let create_table_request = json!({
+      "name": "tablenametest",
+      "location": location,
+      "schema": {
+        "type": "struct",
+        "identifier-field-ids": [10],
+        "fields": [
+          {
+            "id": 20,
+            "name": "point",
+            "required": false,
+            "type": {
+              "type": "struct",
+              "fields": [
+                { "id": 21, "name": "x", "type": "double", "required": true },
+                { "id": 22, "name": "y", "type": "double", "required": true },
+              ],
+            },
+          },
+          { "id": 10, "name": "id", "type": "long", "required": true },
+          {
+            "id": 30,
+            "name": "tags",
+            "required": false,
+            "type": {
+              "type": "map",
+              "key-id": 31,
+              "key": "string",
+              "value-id": 32,
+              "value": { "type": "list", "element-id": 33, "element": "string", "element-required": false },
+              "value-required": false,
+            },
+          },
+        ],
+      },
+      "partition-spec": { "fields": [{ "source-id": 10, "name": "id_bucket", "transform": "bucket[16]" }] },
+      "write-order": {
+        "order-id": 5,
+        "fields": [{ "source-id": 10, "transform": "identity", "direction": "asc", "null-order": "nulls-first" }],
+      },
+      "properties": { "format-version": "1", "owner": "catalog2" },
+    });
+    let response = client
+      .post(format!("/v1/namespaces/{}/tables", namespace_name))
+      .header(ContentType::JSON)
+      .body(create_table_request.to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let metadata = response.into_json::<Value>().await.unwrap()["metadata"].take();
+    assert_eq!(metadata["format-version"], 1);
+    assert_eq!(metadata["location"], location);
+    assert_eq!(metadata["properties"], json!({ "owner": "catalog2" }));
+    assert_eq!(metadata["last-column-id"], 8);
+    let schema = &metadata["schemas"][0];
+    assert_eq!(schema["identifier-field-ids"], json!([2]));
+    assert_eq!(schema["fields"][0]["id"], 1);
+    assert_eq!(schema["fields"][0]["type"]["fields"][0]["id"], 4);
+    assert_eq!(schema["fields"][1]["id"], 2);
+    assert_eq!(schema["fields"][2]["id"], 3);
+    assert_eq!(schema["fields"][2]["type"]["key-id"], 6);
+    assert_eq!(schema["fields"][2]["type"]["value-id"], 7);
+    assert_eq!(schema["fields"][2]["type"]["value"]["element-id"], 8);
+    assert_eq!(
+      metadata["partition-specs"][0],
+      json!({
+        "spec-id": 0,
+        "fields": [{ "field-id": 1000, "source-id": 2, "name": "id_bucket", "transform": "bucket[16]" }],
+      })
+    );
+    assert_eq!(metadata["last-partition-id"], 1000);
+    assert_eq!(metadata["default-sort-order-id"], 1);
+    assert_eq!(metadata["sort-orders"][0]["fields"][0]["source-id"], 2);
+  }
+
+  #[rocket::async_test]
+  async fn test_post_table_by_namespace_invalid_schema() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let namespace_name = "testnamespacename";
+    create_namespace_and_table(&client, namespace_name, "tablenametest").await;
+
+    let invalid_requests = [
+      // unknown primitive type
+      json!({ "name": "t1", "schema": { "type": "struct", "fields": [
+        { "id": 1, "name": "a", "type": "varchar", "required": true },
+      ] } }),
+      // duplicate field names
+      json!({ "name": "t2", "schema": { "type": "struct", "fields": [
+        { "id": 1, "name": "a", "type": "int", "required": true },
+        { "id": 2, "name": "a", "type": "int", "required": true },
+      ] } }),
+      // optional identifier field
+      json!({ "name": "t3", "schema": { "type": "struct", "identifier-field-ids": [1], "fields": [
+        { "id": 1, "name": "a", "type": "int", "required": false },
+      ] } }),
+      // partition source does not exist
+      json!({
+        "name": "t4",
+        "schema": { "type": "struct", "fields": [{ "id": 1, "name": "a", "type": "int", "required": true }] },
+        "partition-spec": { "fields": [{ "source-id": 9, "name": "p", "transform": "identity" }] },
+      }),
+    ];
+    for request in invalid_requests {
+      let response = client
+        .post(format!("/v1/namespaces/{}/tables", namespace_name))
+        .header(ContentType::JSON)
+        .body(request.to_string())
+        .dispatch()
+        .await;
+      assert_eq!(response.status(), Status::BadRequest, "{}", request);
+    }
+  }
+
+  #[rocket::async_test]
+  async fn test_stage_create_table() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let namespace_name = "testnamespacename";
+    let warehouse = std::fs::canonicalize(temp_dir.path().join("warehouse")).unwrap();
+    let namespace_location = warehouse.join(namespace_name);
+    let create_namespace_request = CreateNamespaceRequest {
+      namespace: vec![namespace_name.to_string()],
+      properties: Some(json!({ "location": format!("{}/", namespace_location.display()) })),
+    };
+    let response = client
+      .post("/v1/namespaces")
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&create_namespace_request).unwrap())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let table_name = "ctastable";
+    let mut create_table_request = mock_create_table_request(table_name);
+    create_table_request.stage_create = true;
+    let response = client
+      .post(format!("/v1/namespaces/{}/tables", namespace_name))
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&create_table_request).unwrap())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let staged = response.into_json::<Value>().await.unwrap()["metadata"].take();
+    let location = namespace_location.join(table_name);
+    assert_eq!(staged["location"], location.to_str().unwrap());
+
+    // staged tables are not visible
+    let url = format!("/v1/namespaces/{}/tables/{}", namespace_name, table_name);
+    let head_response = client.head(&url).dispatch().await;
+    assert_eq!(head_response.status(), Status::NotFound);
+
+    let commit_table_request = json!({
+      "requirements": [{ "type": "assert-create" }],
+      "updates": [
+        { "action": "assign-uuid", "uuid": staged["table-uuid"] },
+        { "action": "upgrade-format-version", "format-version": 2 },
+        { "action": "add-schema", "schema": staged["schemas"][0], "last-column-id": 2 },
+        { "action": "set-current-schema", "schema-id": -1 },
+        { "action": "add-spec", "spec": staged["partition-specs"][0] },
+        { "action": "set-default-spec", "spec-id": -1 },
+        { "action": "add-sort-order", "sort-order": staged["sort-orders"][0] },
+        { "action": "set-default-sort-order", "sort-order-id": -1 },
+        { "action": "set-location", "location": staged["location"] },
+        {
+          "action": "add-snapshot",
+          "snapshot": {
+            "snapshot-id": 1,
+            "sequence-number": 1,
+            "timestamp-ms": 1700000000000i64,
+            "manifest-list": location.join("metadata/snap-1.avro").to_str().unwrap(),
+            "summary": { "operation": "append" },
+          },
+        },
+        { "action": "set-snapshot-ref", "ref-name": "main", "type": "branch", "snapshot-id": 1 },
+      ],
+    });
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(commit_table_request.to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let metadata = response.into_json::<Value>().await.unwrap()["metadata"].take();
+    assert_eq!(metadata["table-uuid"], staged["table-uuid"]);
+    assert_eq!(metadata["format-version"], 2);
+    assert_eq!(metadata["current-snapshot-id"], 1);
+    assert_eq!(metadata["schemas"], staged["schemas"]);
+
+    let head_response = client.head(&url).dispatch().await;
+    assert_eq!(head_response.status(), Status::NoContent);
+    let list_response = client
+      .get(format!("/v1/namespaces/{}/tables", namespace_name))
+      .dispatch()
+      .await;
+    let tables = list_response.into_json::<Value>().await.unwrap();
+    assert_eq!(tables["identifiers"][0]["name"], table_name);
+
+    // the table cannot be created twice
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(commit_table_request.to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Conflict);
+  }
+
   #[rocket::async_test]
   async fn test_delete_table_that_exists() {
     let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
@@ -370,14 +740,15 @@ pub(crate) mod test {
 
     assert_eq!(response.status(), Status::Ok);
 
+    // an existing namespace without tables lists none
     let url = format!("/v1/namespaces/{}/tables", namespace_name);
     let get_response = client.get(&url).dispatch().await;
-    assert_eq!(get_response.status(), Status::NotFound);
+    assert_eq!(get_response.status(), Status::Ok);
+    let body = get_response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["identifiers"], json!([]));
 
     let table_name = "tablenametest";
-    let create_table_request = CreateTableRequest {
-      name: table_name.to_string(),
-    };
+    let create_table_request = mock_create_table_request(table_name);
     let create_table_request_json = Json(create_table_request);
     let json_bytes = serde_json::to_vec(&create_table_request_json.into_inner()).unwrap();
 
@@ -421,14 +792,15 @@ pub(crate) mod test {
 
     assert_eq!(response.status(), Status::Ok);
 
+    // an existing namespace without tables lists none
     let url = format!("/v1/namespaces/{}/tables", namespace_name);
     let get_response = client.get(&url).dispatch().await;
-    assert_eq!(get_response.status(), Status::NotFound);
+    assert_eq!(get_response.status(), Status::Ok);
+    let body = get_response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["identifiers"], json!([]));
 
     let table_name = "tablenametest";
-    let create_table_request = CreateTableRequest {
-      name: table_name.to_string(),
-    };
+    let create_table_request = mock_create_table_request(table_name);
     let create_table_request_json = Json(create_table_request);
     let json_bytes = serde_json::to_vec(&create_table_request_json.into_inner()).unwrap();
 
@@ -482,9 +854,7 @@ pub(crate) mod test {
     let head_response = client.head(&url).dispatch().await;
     assert_eq!(head_response.status(), Status::NotFound);
 
-    let create_table_request = CreateTableRequest {
-      name: table_name.to_string(),
-    };
+    let create_table_request = mock_create_table_request(table_name);
     let create_table_request_json = Json(create_table_request);
     let json_bytes = serde_json::to_vec(&create_table_request_json.into_inner()).unwrap();
 
@@ -535,9 +905,7 @@ pub(crate) mod test {
     let head_response = client.head(&url).dispatch().await;
     assert_eq!(head_response.status(), Status::NotFound);
 
-    let create_table_request = CreateTableRequest {
-      name: table_name.to_string(),
-    };
+    let create_table_request = mock_create_table_request(table_name);
     let create_table_request_json = Json(create_table_request);
     let json_bytes = serde_json::to_vec(&create_table_request_json.into_inner()).unwrap();
 
@@ -586,14 +954,1068 @@ pub(crate) mod test {
     assert_eq!(head_response_4.status(), Status::NoContent);
   }
 
+  #[rocket::async_test]
+  async fn test_rename_table_across_namespaces() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    create_namespace_and_table(&client, "a", "t").await;
+    let metadata = create_namespace_and_table(&client, "b", "u").await;
+    let rename = |source: (&str, &str), destination: (&str, &str)| {
+      let request = json!({
+        "source": { "namespace": [source.0], "name": source.1 },
+        "destination": { "namespace": [destination.0], "name": destination.1 },
+      });
+      client
+        .post("/v1/tables/rename")
+        .header(ContentType::JSON)
+        .body(request.to_string())
+        .dispatch()
+    };
+
+    assert_eq!(
+      rename(("a", "t"), ("b", "u")).await.status(),
+      Status::Conflict
+    );
+    assert_eq!(
+      rename(("a", "missing"), ("b", "v")).await.status(),
+      Status::NotFound
+    );
+    assert_eq!(
+      rename(("a", "t"), ("missing", "t")).await.status(),
+      Status::NotFound
+    );
+    assert_eq!(
+      rename(("missing", "t"), ("b", "v")).await.status(),
+      Status::NotFound
+    );
+
+    let response = rename(("b", "u"), ("a", "v")).await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.get("/v1/namespaces/a/tables").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(
+      body["identifiers"],
+      json!([{ "namespace": ["a"], "name": "t" }, { "namespace": ["a"], "name": "v" }])
+    );
+    // the namespace has no tables left
+    let response = client.get("/v1/namespaces/b/tables").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["identifiers"], json!([]));
+    let response = client.get("/v1/namespaces/b/tables/u").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.get("/v1/namespaces/a/tables/v").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["metadata"]["table-uuid"], metadata["table-uuid"]);
+  }
+
+  // Commit a snapshot whose manifest, written under `location`, lists `data_files`.
+  async fn add_snapshot(client: &Client, table_url: &str, location: &Path, data_files: &[&Path]) {
+    let manifest = location.join("metadata/m0.avro");
+    let records: Vec<Value> = data_files
+      .iter()
+      .map(|x| json!({ "status": "1", "data_file": { "file_path": x.to_str().unwrap() } }))
+      .collect();
+    write_records(&manifest, &records, Codec::Null);
+    let manifest_list = location.join("metadata/snap-7.avro");
+    write_records(
+      &manifest_list,
+      &[json!({ "manifest_path": format!("file://{}", manifest.display()) })],
+      Codec::Deflate(DeflateSettings::default()),
+    );
+    let commit = json!({
+      "requirements": [],
+      "updates": [{
+        "action": "add-snapshot",
+        "snapshot": {
+          "snapshot-id": 7,
+          "sequence-number": 1,
+          "timestamp-ms": 1700000000000i64,
+          "manifest-list": manifest_list.to_str().unwrap(),
+          "summary": { "operation": "append" },
+        },
+      }],
+    });
+    let response = client
+      .post(table_url)
+      .header(ContentType::JSON)
+      .body(commit.to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+  }
+
+  // Drop a table with a purge and wait for the purge to finish.
+  async fn purge_table(client: &Client, table_url: &str) -> Value {
+    let response = client
+      .delete(format!("{}?purgeRequested=true", table_url))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NoContent);
+    let mut purge = Value::Null;
+    for _ in 0..100 {
+      let response = client.get(format!("{}/purge", table_url)).dispatch().await;
+      assert_eq!(response.status(), Status::Ok);
+      purge = response.into_json::<Value>().await.unwrap();
+      if purge["status"] == "done" || purge["status"] == "failed" {
+        break;
+      }
+      std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    purge
+  }
+
+  #[rocket::async_test]
+  async fn test_purge_table() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let metadata = create_namespace_and_table(&client, "a", "t").await;
+    let location = PathBuf::from(
+      metadata["location"]
+        .as_str()
+        .unwrap()
+        .trim_start_matches("file://"),
+    );
+    let data_file = location.join("data/p=1/00000.parquet");
+    std::fs::create_dir_all(data_file.parent().unwrap()).unwrap();
+    std::fs::write(&data_file, b"data").unwrap();
+    add_snapshot(
+      &client,
+      "/v1/namespaces/a/tables/t",
+      &location,
+      &[&data_file],
+    )
+    .await;
+
+    // a drop without purge leaves the files in place
+    let response = client
+      .get("/v1/namespaces/a/tables/t/purge")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NotFound);
+    let purge = purge_table(&client, "/v1/namespaces/a/tables/t").await;
+    assert_eq!(purge["status"], "done", "{}", purge);
+    assert_eq!(purge["table-uuid"], metadata["table-uuid"]);
+    // the data file, the manifest, the manifest list and the two metadata files
+    assert_eq!(purge["deleted-files"], 5);
+    assert!(!location.exists());
+
+    let metadata = create_namespace_and_table(&client, "b", "t").await;
+    let response = client.delete("/v1/namespaces/b/tables/t").dispatch().await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client
+      .get("/v1/namespaces/b/tables/t/purge")
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NotFound);
+    let location = metadata["location"]
+      .as_str()
+      .unwrap()
+      .trim_start_matches("file://");
+    assert!(PathBuf::from(location).join("metadata").exists());
+  }
+
+  #[rocket::async_test]
+  async fn test_purge_outside_table_location() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+    let root = std::fs::canonicalize(temp_dir.path()).unwrap();
+
+    // the manifest of a client can list the files of other tables or of anything else
+    let metadata = create_namespace_and_table(&client, "a", "t").await;
+    let location = PathBuf::from(
+      metadata["location"]
+        .as_str()
+        .unwrap()
+        .trim_start_matches("file://"),
+    );
+    let data_file = location.join("data/00000.parquet");
+    let outside = root.join("outside.parquet");
+    let escaping = location.join("../u/00000.parquet");
+    for file in [&data_file, &outside, &escaping] {
+      std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+      std::fs::write(file, b"data").unwrap();
+    }
+    let table_url = "/v1/namespaces/a/tables/t";
+    add_snapshot(
+      &client,
+      table_url,
+      &location,
+      &[&data_file, &outside, &escaping],
+    )
+    .await;
+    let purge = purge_table(&client, table_url).await;
+    assert_eq!(purge["status"], "failed", "{}", purge);
+    assert_eq!(
+      purge["failed-files"],
+      json!([outside.to_str().unwrap(), escaping.to_str().unwrap()])
+    );
+    assert_eq!(purge["deleted-files"], 5);
+    assert!(!data_file.exists());
+    assert!(outside.exists());
+    // `location` itself is removed once empty
+    assert!(location.parent().unwrap().join("u/00000.parquet").exists());
+  }
+
+  #[rocket::async_test]
+  async fn test_table_location_outside_warehouse() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+    let warehouse = std::fs::canonicalize(temp_dir.path().join("warehouse")).unwrap();
+    let outside = std::fs::canonicalize(temp_dir.path())
+      .unwrap()
+      .join("outside");
+    let create = |location: Option<String>, properties: Value| {
+      let mut request = serde_json::to_value(mock_create_table_request("t")).unwrap();
+      if let Some(location) = location {
+        request["location"] = json!(location);
+      }
+      request["properties"] = properties;
+      client
+        .post("/v1/namespaces/a/tables")
+        .header(ContentType::JSON)
+        .body(request.to_string())
+        .dispatch()
+    };
+
+    create_namespace_and_table(&client, "a", "u").await;
+    let invalid = [
+      (Some(outside.display().to_string()), json!({})),
+      (Some(format!("file://{}", outside.display())), json!({})),
+      (
+        Some(format!("{}/../outside", warehouse.display())),
+        json!({}),
+      ),
+      (
+        None,
+        json!({ "write.metadata.path": outside.display().to_string() }),
+      ),
+    ];
+    for (location, properties) in invalid {
+      let response = create(location.clone(), properties).await;
+      assert_eq!(response.status(), Status::BadRequest, "{:?}", location);
+    }
+    let commit = json!({
+      "requirements": [],
+      "updates": [{ "action": "set-location", "location": outside.display().to_string() }],
+    });
+    let response = client
+      .post("/v1/namespaces/a/tables/u")
+      .header(ContentType::JSON)
+      .body(commit.to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(!outside.exists());
+
+    // nothing is written to other file systems
+    let response = create(Some("s3://bucket/t".to_string()), json!({})).await;
+    assert_eq!(response.status(), Status::Ok);
+  }
+
+  #[rocket::async_test]
+  async fn test_get_table() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let namespace_name = "namespacenametest";
+    let table_name = "tablenametest";
+    let created = create_namespace_and_table(&client, namespace_name, table_name).await;
+
+    let url = format!("/v1/namespaces/{}/tables/{}", namespace_name, table_name);
+    let get_response = client.get(&url).dispatch().await;
+    assert_eq!(get_response.status(), Status::Ok);
+    let metadata = get_response.into_json::<Value>().await.unwrap()["metadata"].take();
+    assert_eq!(metadata, created);
+    for field in [
+      "format-version",
+      "table-uuid",
+      "last-updated-ms",
+      "schemas",
+      "current-schema-id",
+      "partition-specs",
+      "default-spec-id",
+      "sort-orders",
+      "default-sort-order-id",
+      "snapshot-log",
+      "metadata-log",
+    ] {
+      assert!(metadata.get(field).is_some(), "missing {}", field);
+    }
+
+    let url = format!(
+      "/v1/namespaces/{}/tables/{}",
+      namespace_name, "tablenamenotexist"
+    );
+    let get_response = client.get(&url).dispatch().await;
+    assert_eq!(get_response.status(), Status::NotFound);
+  }
+
+  #[rocket::async_test]
+  async fn test_register_table() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let namespace_name = "namespacenametest";
+    create_namespace_and_table(&client, namespace_name, "tablenametest").await;
+
+    // a v1 metadata file with a snapshot but no refs
+    let metadata_path = temp_dir.path().join("00001-test.metadata.json");
+    let metadata = json!({
+      "format-version": 1,
+      "table-uuid": "4f6bd9e2-6bd4-4a3b-a4fd-0b7c3e1ad4c8",
+      "location": "s3://bucket/registered",
+      "last-updated-ms": 1602638573874i64,
+      "last-column-id": 2,
+      "schema": {
+        "type": "struct",
+        "fields": [
+          {"id": 1, "name": "id", "required": true, "type": "long"},
+          {"id": 2, "name": "data", "required": false, "type": "string"}
+        ]
+      },
+      "partition-spec": [
+        {"name": "id_bucket", "transform": "bucket[16]", "source-id": 1, "field-id": 1000}
+      ],
+      "properties": {},
+      "current-snapshot-id": 3051729675574597004i64,
+      "snapshots": [{
+        "snapshot-id": 3051729675574597004i64,
+        "timestamp-ms": 1515100955770i64,
+        "summary": {"operation": "append"},
+        "manifest-list": "s3://bucket/registered/metadata/snap-1.avro"
+      }]
+    });
+    std::fs::write(&metadata_path, serde_json::to_vec(&metadata).unwrap()).unwrap();
+    let metadata_location = format!("file://{}", metadata_path.display());
+
+    let url = format!("/v1/namespaces/{}/register", namespace_name);
+    let register = |name: &str, location: &str| {
+      serde_json::to_vec(&RegisterTableRequest {
+        name: name.to_string(),
+        metadata_location: location.to_string(),
+      })
+      .unwrap()
+    };
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(register("registered", &metadata_location))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["metadata-location"], metadata_location);
+    assert_eq!(
+      body["metadata"]["table-uuid"],
+      "4f6bd9e2-6bd4-4a3b-a4fd-0b7c3e1ad4c8"
+    );
+    assert_eq!(body["metadata"]["current-schema-id"], 0);
+    assert_eq!(body["metadata"]["default-spec-id"], 0);
+    assert_eq!(body["metadata"]["last-partition-id"], 1000);
+    assert_eq!(
+      body["metadata"]["refs"]["main"]["snapshot-id"],
+      3051729675574597004i64
+    );
+
+    // the registered table can be loaded and shows up in the listing
+    let response = client
+      .get(format!(
+        "/v1/namespaces/{}/tables/registered",
+        namespace_name
+      ))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let loaded = response.into_json::<Value>().await.unwrap();
+    assert_eq!(loaded, body);
+
+    // registering the same name again conflicts, plain paths work too
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(register("registered", metadata_path.to_str().unwrap()))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Conflict);
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(register("plainpath", metadata_path.to_str().unwrap()))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let missing = temp_dir.path().join("missing.metadata.json");
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(register("missingfile", missing.to_str().unwrap()))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    let response = client
+      .post("/v1/namespaces/namespacenotexist/register")
+      .header(ContentType::JSON)
+      .body(register("registered", &metadata_location))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    // metadata pointing to a schema that does not exist is rejected
+    let mut invalid = metadata.clone();
+    invalid["current-schema-id"] = json!(5);
+    invalid["schemas"] = json!([invalid["schema"].clone()]);
+    std::fs::write(&metadata_path, serde_json::to_vec(&invalid).unwrap()).unwrap();
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(register("invalid", &metadata_location))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::BadRequest);
+  }
+
+  #[rocket::async_test]
+  async fn test_commit_table() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let namespace_name = "namespacenametest";
+    let table_name = "tablenametest";
+    let metadata = create_namespace_and_table(&client, namespace_name, table_name).await;
+    let table_uuid = metadata["table-uuid"].as_str().unwrap();
+
+    let commit_table_request = json!({
+      "requirements": [
+        { "type": "assert-table-uuid", "uuid": table_uuid },
+        { "type": "assert-current-schema-id", "current-schema-id": 0 },
+      ],
+      "updates": [
+        {
+          "action": "add-schema",
+          "schema": {
+            "type": "struct",
+            "schema-id": 1,
+            "fields": [
+              { "id": 1, "name": "id", "type": "long", "required": true },
+              { "id": 2, "name": "data", "type": "string", "required": false },
+              { "id": 3, "name": "ts", "type": "timestamptz", "required": false },
+            ],
+          },
+          "last-column-id": 3,
+        },
+        { "action": "set-current-schema", "schema-id": -1 },
+        { "action": "set-properties", "updates": { "owner": "catalog2" } },
+      ],
+    });
+
+    let url = format!("/v1/namespaces/{}/tables/{}", namespace_name, table_name);
+    let commit_response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(commit_table_request.to_string())
+      .dispatch()
+      .await;
+    assert_eq!(commit_response.status(), Status::Ok);
+
+    let get_response = client.get(&url).dispatch().await;
+    assert_eq!(get_response.status(), Status::Ok);
+    let body = get_response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["metadata"]["current-schema-id"], 1);
+    assert_eq!(body["metadata"]["last-column-id"], 3);
+    assert_eq!(body["metadata"]["properties"]["owner"], "catalog2");
+  }
+
+  #[rocket::async_test]
+  async fn test_commit_table_writes_metadata_files() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let namespace_name = "namespacenametest";
+    let table_name = "tablenametest";
+    let url = format!("/v1/namespaces/{}/tables/{}", namespace_name, table_name);
+    create_namespace_and_table(&client, namespace_name, table_name).await;
+    let response = client.get(&url).dispatch().await;
+    let created = response.into_json::<Value>().await.unwrap();
+
+    // new tables go under the warehouse and start at version 0
+    let warehouse = std::fs::canonicalize(temp_dir.path().join("warehouse")).unwrap();
+    let table_location = format!(
+      "file://{}/{}/{}",
+      warehouse.display(),
+      namespace_name,
+      table_name
+    );
+    assert_eq!(created["metadata"]["location"], table_location);
+    let first_location = created["metadata-location"].as_str().unwrap().to_string();
+    assert!(first_location.starts_with(&format!("{}/metadata/00000-", table_location)));
+    assert!(first_location.ends_with(".metadata.json"));
+    let on_disk = crate::catalog::file_io::read_metadata(&first_location).unwrap();
+    assert_eq!(serde_json::to_value(on_disk).unwrap(), created["metadata"]);
+
+    let commit = |updates: Value| json!({ "requirements": [], "updates": updates });
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(
+        commit(json!([
+          { "action": "set-properties", "updates": {
+            "write.metadata.previous-versions-max": "1"
+          } },
+        ]))
+        .to_string(),
+      )
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    let second_location = body["metadata-location"].as_str().unwrap().to_string();
+    assert!(second_location.starts_with(&format!("{}/metadata/00001-", table_location)));
+    assert_eq!(
+      body["metadata"]["metadata-log"],
+      json!([{
+        "metadata-file": first_location,
+        "timestamp-ms": created["metadata"]["last-updated-ms"],
+      }])
+    );
+    let on_disk = crate::catalog::file_io::read_metadata(&second_location).unwrap();
+    assert_eq!(serde_json::to_value(on_disk).unwrap(), body["metadata"]);
+
+    // the metadata log only keeps the configured number of previous files
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(commit(json!([{ "action": "remove-properties", "removals": ["owner"] }])).to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    assert!(body["metadata-location"]
+      .as_str()
+      .unwrap()
+      .starts_with(&format!("{}/metadata/00002-", table_location)));
+    let metadata_log = body["metadata"]["metadata-log"].as_array().unwrap();
+    assert_eq!(metadata_log.len(), 1);
+    assert_eq!(metadata_log[0]["metadata-file"], second_location);
+
+    let response = client.get(&url).dispatch().await;
+    let loaded = response.into_json::<Value>().await.unwrap();
+    assert_eq!(loaded["metadata-location"], body["metadata-location"]);
+    assert!(std::path::Path::new(first_location.strip_prefix("file://").unwrap()).exists());
+  }
+
+  #[rocket::async_test]
+  async fn test_table_uuids_across_restarts() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let namespace_name = "namespacenametest";
+    let url = format!("/v1/namespaces/{}/tables/legacy", namespace_name);
+
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+    let metadata = create_namespace_and_table(&client, namespace_name, "legacy").await;
+    assert!(uuid::Uuid::parse_str(metadata["table-uuid"].as_str().unwrap()).is_ok());
+    // simulate a table created by an older version of the catalog
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(
+        json!({
+          "requirements": [],
+          "updates": [{ "action": "assign-uuid", "uuid": "uuid0" }],
+        })
+        .to_string(),
+      )
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    drop(response);
+    drop(client);
+    // older versions of the catalog did not mark the migration as done
+    let migration = Key::Migration("table-uuids".to_string()).encode();
+    let store = Backend::default()
+      .open(temp_dir.path(), "namespace", "default")
+      .unwrap();
+    assert!(store.get(&migration).unwrap().is_some());
+    store.delete(&migration).unwrap();
+    drop(store);
+
+    // the legacy uuid is replaced when the catalog starts again
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+    let response = client.get(&url).dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    let migrated = body["metadata"]["table-uuid"].as_str().unwrap().to_string();
+    assert!(uuid::Uuid::parse_str(&migrated).is_ok());
+    let metadata_log = body["metadata"]["metadata-log"].as_array().unwrap();
+    assert_eq!(metadata_log.len(), 2);
+
+    let response = client
+      .post(format!("/v1/namespaces/{}/tables", namespace_name))
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&mock_create_table_request("fresh")).unwrap())
+      .dispatch()
+      .await;
+    let body = response.into_json::<Value>().await.unwrap();
+    let fresh = body["metadata"]["table-uuid"].as_str().unwrap();
+    assert_ne!(fresh, migrated);
+    assert_ne!(fresh, metadata["table-uuid"].as_str().unwrap());
+  }
+
+  #[rocket::async_test]
+  async fn test_redb_backend() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let create_client = || {
+      create_mock_client_with_backend(
+        temp_dir.path().to_path_buf(),
+        config::ServerConfig::default(),
+        Backend::Redb,
+      )
+    };
+    {
+      let client = create_client().await;
+      create_namespace_and_table(&client, "a", "t1").await;
+      let response = client
+        .post("/v1/namespaces/a/tables")
+        .header(ContentType::JSON)
+        .body(serde_json::to_vec(&mock_create_table_request("t2")).unwrap())
+        .dispatch()
+        .await;
+      assert_eq!(response.status(), Status::Ok);
+      let response = client.delete("/v1/namespaces/a/tables/t1").dispatch().await;
+      assert_eq!(response.status(), Status::NoContent);
+    }
+
+    let client = create_client().await;
+    let response = client.get("/v1/namespaces/a/tables").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(
+      body["identifiers"],
+      json!([{ "namespace": ["a"], "name": "t2" }])
+    );
+    let response = client.get("/v1/namespaces/a/tables/t2").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert!(temp_dir.path().join("namespace.redb").exists());
+    assert!(!temp_dir.path().join("catalog.namespace").exists());
+  }
+
+  #[rocket::async_test]
+  async fn test_sqlite_backend_shared_with_jdbc() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client_with_backend(
+      temp_dir.path().to_path_buf(),
+      config::ServerConfig::default(),
+      Backend::Sqlite,
+    )
+    .await;
+    let url = "/v1/namespaces/a/tables/t";
+    create_namespace_and_table(&client, "a", "t").await;
+    let response = client.get(url).dispatch().await;
+    let created = response.into_json::<Value>().await.unwrap();
+    let first_location = created["metadata-location"].as_str().unwrap().to_string();
+
+    // a JDBC client commits a new metadata file and registers a table of its own
+    let jdbc = rusqlite::Connection::open(temp_dir.path().join("namespace.sqlite")).unwrap();
+    let location: String = jdbc
+      .query_row(
+        "SELECT metadata_location FROM iceberg_tables
+         WHERE catalog_name = 'default' AND table_namespace = 'a' AND table_name = 't'",
+        [],
+        |row| row.get(0),
+      )
+      .unwrap();
+    assert_eq!(location, first_location);
+    let second_location = first_location.replace("/00000-", "/00001-");
+    let mut metadata: TableMetadata = serde_json::from_value(created["metadata"].clone()).unwrap();
+    metadata
+      .properties
+      .insert("committed-by".to_string(), "jdbc".to_string());
+    crate::catalog::file_io::write_metadata(&second_location, &metadata).unwrap();
+    jdbc
+      .execute(
+        "UPDATE iceberg_tables SET metadata_location = ?1, previous_metadata_location = ?2
+         WHERE catalog_name = 'default' AND table_namespace = 'a' AND table_name = 't'",
+        [&second_location, &first_location],
+      )
+      .unwrap();
+    jdbc
+      .execute(
+        "INSERT INTO iceberg_tables VALUES ('default', 'a', 'other', ?1, NULL, 'TABLE')",
+        [&second_location],
+      )
+      .unwrap();
+
+    let response = client.get(url).dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["metadata-location"], second_location);
+    assert_eq!(body["metadata"]["properties"]["committed-by"], "jdbc");
+    let response = client.get("/v1/namespaces/a/tables").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(
+      body["identifiers"],
+      json!([
+        { "namespace": ["a"], "name": "other" },
+        { "namespace": ["a"], "name": "t" },
+      ])
+    );
+
+    // commits start from the metadata the JDBC client wrote
+    let response = client
+      .post(url)
+      .header(ContentType::JSON)
+      .body(
+        json!({
+          "requirements": [],
+          "updates": [{ "action": "set-properties", "updates": { "owner": "rest" } }],
+        })
+        .to_string(),
+      )
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["metadata"]["properties"]["committed-by"], "jdbc");
+    let row: (String, String) = jdbc
+      .query_row(
+        "SELECT metadata_location, previous_metadata_location FROM iceberg_tables
+         WHERE catalog_name = 'default' AND table_namespace = 'a' AND table_name = 't'",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+      )
+      .unwrap();
+    assert_eq!(
+      row,
+      (
+        body["metadata-location"].as_str().unwrap().to_string(),
+        second_location
+      )
+    );
+  }
+
+  #[rocket::async_test]
+  async fn test_legacy_keys_migration() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().join("source")).await;
+    let metadata = create_namespace_and_table(&client, "a", "x_y").await;
+
+    // a catalog written before keys were escaped
+    let legacy_dir = temp_dir.path().join("legacy");
+    std::fs::create_dir(&legacy_dir).unwrap();
+    let mut legacy = pickledb::PickleDb::new(
+      legacy_dir.join("catalog.namespace"),
+      pickledb::PickleDbDumpPolicy::AutoDump,
+      pickledb::SerializationMethod::Json,
+    );
+    let namespace = |child: &[&str], tables: &[&str]| json!({ "child": child, "properties": {}, "tables": tables });
+    let table = |name: &str| json!({ "name": name, "metadata": metadata });
+    legacy.set("root", &namespace(&["a"], &[])).unwrap();
+    legacy.set("root::a", &namespace(&["b"], &["x_y"])).unwrap();
+    legacy.set("root::a::b", &namespace(&[], &["t"])).unwrap();
+    legacy.set("root::a_x_y", &table("x_y")).unwrap();
+    legacy.set("root::a::b_t", &table("t")).unwrap();
+    let mut legacy_metrics = pickledb::PickleDb::new(
+      legacy_dir.join("catalog.metrics"),
+      pickledb::PickleDbDumpPolicy::AutoDump,
+      pickledb::SerializationMethod::Json,
+    );
+    let report = json!([{
+      "received-ms": 1,
+      "report-type": "commit-report",
+      "table-name": "x_y",
+      "snapshot-id": 1,
+      "sequence-number": 1,
+      "operation": "append",
+      "metrics": {},
+    }]);
+    legacy_metrics.set("root::a_x_y", &report).unwrap();
+    drop(legacy);
+    drop(legacy_metrics);
+
+    let client = create_mock_client(legacy_dir.clone()).await;
+    let response = client.get("/v1/namespaces?parent=a").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["namespaces"], json!([["a", "b"]]));
+    for url in [
+      "/v1/namespaces/a/tables/x_y",
+      "/v1/namespaces/a%1Fb/tables/t",
+    ] {
+      let response = client.get(url).dispatch().await;
+      assert_eq!(response.status(), Status::Ok, "{}", url);
+    }
+    let response = client
+      .get("/v1/namespaces/a/tables/x_y/metrics")
+      .dispatch()
+      .await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["reports"].as_array().unwrap().len(), 1);
+
+    // no legacy key is left behind
+    let migrated = pickledb::PickleDb::load_read_only(
+      legacy_dir.join("catalog.namespace"),
+      pickledb::SerializationMethod::Json,
+    )
+    .unwrap();
+    assert!(migrated
+      .get_all()
+      .iter()
+      .all(|key| !key.starts_with("root")));
+  }
+
+  #[rocket::async_test]
+  async fn test_commit_table_requirement_failed() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let namespace_name = "namespacenametest";
+    let table_name = "tablenametest";
+    create_namespace_and_table(&client, namespace_name, table_name).await;
+
+    let commit_table_request = json!({
+      "requirements": [{ "type": "assert-current-schema-id", "current-schema-id": 7 }],
+      "updates": [{ "action": "set-properties", "updates": { "owner": "catalog2" } }],
+    });
+
+    let url = format!("/v1/namespaces/{}/tables/{}", namespace_name, table_name);
+    let commit_response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(commit_table_request.to_string())
+      .dispatch()
+      .await;
+    assert_eq!(commit_response.status(), Status::Conflict);
+
+    // nothing was applied
+    let get_response = client.get(&url).dispatch().await;
+    let body = get_response.into_json::<Value>().await.unwrap();
+    assert!(body["metadata"]["properties"].get("owner").is_none());
+  }
+
+  #[rocket::async_test]
+  async fn test_commit_transaction() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    create_namespace_and_table(&client, "ns", "fact").await;
+    let response = client
+      .post("/v1/namespaces/ns/tables")
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&mock_create_table_request("dim")).unwrap())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let change = |table: &str, requirements: Value, owner: &str| {
+      json!({
+        "identifier": { "namespace": ["ns"], "name": table },
+        "requirements": requirements,
+        "updates": [{ "action": "set-properties", "updates": { "owner": owner } }],
+      })
+    };
+    let commit = |table_changes: Vec<Value>| {
+      client
+        .post("/v1/transactions/commit")
+        .header(ContentType::JSON)
+        .body(json!({ "table-changes": table_changes }).to_string())
+        .dispatch()
+    };
+    let owner = |table: &'static str| {
+      let client = &client;
+      async move {
+        let url = format!("/v1/namespaces/ns/tables/{}", table);
+        let body = client
+          .get(url)
+          .dispatch()
+          .await
+          .into_json::<Value>()
+          .await
+          .unwrap();
+        body["metadata"]["properties"]["owner"].clone()
+      }
+    };
+
+    let response = commit(vec![
+      change("fact", json!([]), "etl"),
+      change("dim", json!([]), "etl"),
+    ])
+    .await;
+    assert_eq!(response.status(), Status::NoContent);
+    assert_eq!(owner("fact").await, "etl");
+    assert_eq!(owner("dim").await, "etl");
+
+    // a failed requirement of one table leaves all the tables unchanged
+    let response = commit(vec![
+      change("fact", json!([]), "other"),
+      change(
+        "dim",
+        json!([{ "type": "assert-current-schema-id", "current-schema-id": 7 }]),
+        "other",
+      ),
+    ])
+    .await;
+    assert_eq!(response.status(), Status::Conflict);
+    assert_eq!(owner("fact").await, "etl");
+    assert_eq!(owner("dim").await, "etl");
+
+    // so does a missing table
+    let response = commit(vec![
+      change("fact", json!([]), "other"),
+      change("missing", json!([]), "other"),
+    ])
+    .await;
+    assert_eq!(response.status(), Status::NotFound);
+    assert_eq!(owner("fact").await, "etl");
+
+    let mut unnamed = change("fact", json!([]), "other");
+    unnamed.as_object_mut().unwrap().remove("identifier");
+    let response = commit(vec![unnamed]).await;
+    assert_eq!(response.status(), Status::BadRequest);
+    assert_eq!(owner("fact").await, "etl");
+  }
+
+  #[rocket::async_test]
+  async fn test_commit_table_snapshot() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    let namespace_name = "namespacenametest";
+    let table_name = "tablenametest";
+    create_namespace_and_table(&client, namespace_name, table_name).await;
+    let url = format!("/v1/namespaces/{}/tables/{}", namespace_name, table_name);
+
+    let append = |snapshot_id: i64, parent: Option<i64>| {
+      json!({
+        "requirements": [{ "type": "assert-ref-snapshot-id", "ref": "main", "snapshot-id": parent }],
+        "updates": [
+          {
+            "action": "add-snapshot",
+            "snapshot": {
+              "snapshot-id": snapshot_id,
+              "parent-snapshot-id": parent,
+              "sequence-number": snapshot_id,
+              "timestamp-ms": 1700000000000i64,
+              "manifest-list": format!("/tmp/snap-{}.avro", snapshot_id),
+              "summary": { "operation": "append" },
+            },
+          },
+          { "action": "set-snapshot-ref", "ref-name": "main", "type": "branch", "snapshot-id": snapshot_id },
+        ],
+      })
+      .to_string()
+    };
+
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(append(1, None))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["metadata"]["current-snapshot-id"], 1);
+    assert_eq!(body["metadata"]["refs"]["main"]["snapshot-id"], 1);
+
+    // a writer that has not seen snapshot 1 must be rejected
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(append(2, None))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Conflict);
+
+    let response = client
+      .post(&url)
+      .header(ContentType::JSON)
+      .body(append(2, Some(1)))
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["metadata"]["current-snapshot-id"], 2);
+    assert_eq!(body["metadata"]["last-sequence-number"], 2);
+  }
+
+  // A create table request with a small flat schema.
+  pub fn mock_create_table_request(name: &str) -> CreateTableRequest {
+    serde_json::from_value(json!({
+      "name": name,
+      "schema": {
+        "type": "struct",
+        "fields": [
+          { "id": 1, "name": "id", "type": "long", "required": true },
+          { "id": 2, "name": "data", "type": "string", "required": false },
+        ],
+      },
+    }))
+    .unwrap()
+  }
+
+  // Create a namespace with a single table in it and return the table metadata.
+  pub async fn create_namespace_and_table(
+    client: &Client,
+    namespace_name: &str,
+    table_name: &str,
+  ) -> Value {
+    let create_namespace_request = CreateNamespaceRequest {
+      namespace: vec![namespace_name.to_string()],
+      properties: None,
+    };
+    let response = client
+      .post("/v1/namespaces")
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&create_namespace_request).unwrap())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+
+    let create_table_request = mock_create_table_request(table_name);
+    let response = client
+      .post(format!("/v1/namespaces/{}/tables", namespace_name))
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&create_table_request).unwrap())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Ok);
+    response.into_json::<Value>().await.unwrap()["metadata"].take()
+  }
+
   pub async fn create_mock_client(temp_dir: PathBuf) -> Client {
-    let db_test = DB::new(temp_dir).expect("failed to create a db");
+    create_mock_client_with_config(temp_dir, config::ServerConfig::default()).await
+  }
 
-    let table_metadata_generator = TableMetadataAtomicIncr::new();
+  pub async fn create_mock_client_with_config(
+    temp_dir: PathBuf,
+    server_config: config::ServerConfig,
+  ) -> Client {
+    create_mock_client_with_backend(temp_dir, server_config, Backend::default()).await
+  }
+
+  pub async fn create_mock_client_with_backend(
+    temp_dir: PathBuf,
+    server_config: config::ServerConfig,
+    backend: Backend,
+  ) -> Client {
+    let warehouse_root = temp_dir.join("warehouse");
+    let default_catalog = Catalog {
+      db: DB::new(temp_dir.clone(), backend, "default").expect("failed to create a db"),
+      warehouse: Warehouse::new(warehouse_root.clone()).expect("failed to create a warehouse"),
+    };
+    let catalogs = Catalogs::new(
+      default_catalog,
+      &server_config,
+      &temp_dir,
+      &warehouse_root,
+      backend,
+    )
+    .expect("failed to create the catalogs");
+
+    let table_metadata_generator = TableMetadataGenerator::new();
     let mut rocket = rocket::build();
     rocket = rocket
-      .manage(db_test)
+      .manage(catalogs)
       .manage(table_metadata_generator)
+      .manage(server_config)
+      .attach(prefix::stage())
       .attach(namespace::stage())
       .attach(catches::stage())
       .mount(
@@ -604,11 +2026,23 @@ pub(crate) mod test {
           table::register_table,
           table::get_table,
           table::post_table,
+          table::commit_transaction,
           table::delete_table,
+          table::get_purge,
           table::head_table,
           table::rename_table,
+          view::get_views_by_namespace,
+          view::post_view_by_namespace,
+          view::get_view,
+          view::post_view,
+          view::delete_view,
+          view::head_view,
+          view::rename_view,
           metric::post_metrics,
+          metric::get_metrics,
           config::get_config,
+          admin::list_dropped,
+          admin::restore_dropped,
         ],
       );
 
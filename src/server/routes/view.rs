@@ -0,0 +1,431 @@
+use crate::request::*;
+use crate::server::routes::common::*;
+use crate::{err, ok_empty, response::*};
+use rocket::serde::json::Json;
+
+use crate::catalog::view::View;
+use crate::common::result::{EmptyResult, ErrorType, Location};
+use crate::server::prefix::{CatalogDB, CatalogWarehouse};
+use crate::server::routes::namespace::NamespaceParam;
+use crate::server::routes::table::JsonResultGeneric;
+
+fn load_view_result(view: View) -> Json<LoadViewResult> {
+  Json(LoadViewResult {
+    metadata_location: view.metadata_location,
+    metadata: view.metadata,
+  })
+}
+
+/// List all view identifiers underneath a given namespace
+#[get("/namespaces/<namespace>/views")]
+pub fn get_views_by_namespace(
+  namespace: NamespaceParam,
+  db: CatalogDB<'_>,
+) -> JsonResultGeneric<ListTablesResponse> {
+  let conn = db.conn();
+  let view_names = match View::list(conn, &namespace.0)? {
+    Some(view_names) => view_names,
+    None => {
+      return err!(
+        ErrorType::NotFound,
+        Location::Namespace,
+        format!("Namespace {} not found", namespace.0.join("."))
+      )
+    }
+  };
+  let identifiers = view_names
+    .into_iter()
+    .map(|name| TableIdentifier {
+      namespace: NamespaceResponse(namespace.0.clone()),
+      name,
+    })
+    .collect();
+  Ok(Json(ListTablesResponse {
+    identifiers,
+    next_page_token: None,
+  }))
+}
+
+/// Create a view in the given namespace
+#[post("/namespaces/<namespace>/views", data = "<create_view_request>")]
+pub fn post_view_by_namespace(
+  namespace: NamespaceParam,
+  create_view_request: Json<CreateViewRequest>,
+  db: CatalogDB<'_>,
+  warehouse: CatalogWarehouse<'_>,
+) -> JsonResultGeneric<LoadViewResult> {
+  let conn = db.conn();
+  let view = View::create(conn, &namespace.0, &create_view_request, &warehouse)?;
+  Ok(load_view_result(view))
+}
+
+/// Load a view from the catalog
+#[get("/namespaces/<namespace>/views/<view>")]
+pub fn get_view(
+  namespace: NamespaceParam,
+  view: &str,
+  db: CatalogDB<'_>,
+) -> JsonResultGeneric<LoadViewResult> {
+  let conn = db.conn();
+  match View::get(conn, &namespace.0, view)? {
+    Some(view) => Ok(load_view_result(view)),
+    None => err!(
+      ErrorType::NotFound,
+      Location::View,
+      format!("View {} not found", view)
+    ),
+  }
+}
+
+/// Replace a view: commit updates to its metadata
+#[post("/namespaces/<namespace>/views/<view>", data = "<commit_view_request>")]
+pub fn post_view(
+  namespace: NamespaceParam,
+  view: &str,
+  commit_view_request: Json<CommitViewRequest>,
+  db: CatalogDB<'_>,
+  warehouse: CatalogWarehouse<'_>,
+) -> JsonResultGeneric<LoadViewResult> {
+  let conn = db.conn();
+  let commit_view_request = commit_view_request.into_inner();
+  let view = View::commit(
+    conn,
+    &namespace.0,
+    view.to_string(),
+    &commit_view_request.requirements,
+    commit_view_request.updates,
+    &warehouse,
+  )?;
+  Ok(load_view_result(view))
+}
+
+/// Drop a view from the catalog
+#[delete("/namespaces/<namespace>/views/<view>")]
+pub fn delete_view(namespace: NamespaceParam, view: &str, db: CatalogDB<'_>) -> EmptyResult {
+  let conn = db.conn();
+  View::delete(conn, &namespace.0, view.to_string())?;
+  ok_empty!()
+}
+
+/// Check if a view exists
+#[head("/namespaces/<namespace>/views/<view>")]
+pub fn head_view(namespace: NamespaceParam, view: &str, db: CatalogDB<'_>) -> EmptyResult {
+  let conn = db.conn();
+  match View::exists(conn, &namespace.0, view)? {
+    true => ok_empty!(),
+    false => err!(
+      ErrorType::NotFound,
+      Location::View,
+      "View not found".to_string()
+    ),
+  }
+}
+
+/// Rename a view from its current name to a new name, possibly in another namespace
+#[post("/views/rename", data = "<rename_view_request>")]
+pub fn rename_view(
+  rename_view_request: Json<RenameTableRequest>,
+  db: CatalogDB<'_>,
+) -> EmptyResult {
+  let conn = db.conn();
+  let RenameTableRequest {
+    source,
+    destination,
+  } = rename_view_request.into_inner();
+  View::rename(
+    conn,
+    &source.namespace.0,
+    source.name,
+    &destination.namespace.0,
+    destination.name,
+  )?;
+  ok_empty!()
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+  use super::*;
+  use crate::table::test::{
+    create_mock_client, create_namespace_and_table, mock_create_table_request,
+  };
+  use rocket::http::ContentType;
+  use rocket::http::Status;
+  use rocket::local::asynchronous::Client;
+  use rocket::serde::json::{json, Value};
+
+  pub fn mock_create_view_request(name: &str) -> CreateViewRequest {
+    serde_json::from_value(json!({
+      "name": name,
+      "schema": {
+        "type": "struct",
+        "schema-id": 7,
+        "fields": [{ "id": 1, "name": "id", "type": "long", "required": true }],
+      },
+      "view-version": {
+        "version-id": 7,
+        "timestamp-ms": 1,
+        "schema-id": 7,
+        "summary": { "engine-name": "spark" },
+        "representations": [{ "type": "sql", "sql": "SELECT id FROM t", "dialect": "spark" }],
+        "default-namespace": ["ns"],
+      },
+      "properties": { "comment": "test" },
+    }))
+    .unwrap()
+  }
+
+  async fn create_view(client: &Client, namespace: &str, name: &str) -> Status {
+    client
+      .post(format!("/v1/namespaces/{}/views", namespace))
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&mock_create_view_request(name)).unwrap())
+      .dispatch()
+      .await
+      .status()
+  }
+
+  async fn commit_view(client: &Client, url: &str, request: Value) -> (Status, Value) {
+    let response = client
+      .post(url)
+      .header(ContentType::JSON)
+      .body(request.to_string())
+      .dispatch()
+      .await;
+    let status = response.status();
+    (
+      status,
+      response.into_json::<Value>().await.unwrap_or_default(),
+    )
+  }
+
+  #[rocket::async_test]
+  async fn test_create_view() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    assert_eq!(create_view(&client, "ns", "v").await, Status::NotFound);
+    create_namespace_and_table(&client, "ns", "t").await;
+    assert_eq!(create_view(&client, "ns", "v").await, Status::Ok);
+
+    let response = client.get("/v1/namespaces/ns/views/v").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    let metadata = &body["metadata"];
+    assert_eq!(metadata["format-version"], 1);
+    assert_eq!(metadata["current-version-id"], 1);
+    assert_eq!(metadata["schemas"][0]["schema-id"], 0);
+    assert_eq!(metadata["versions"][0]["version-id"], 1);
+    assert_eq!(metadata["versions"][0]["schema-id"], 0);
+    assert_eq!(
+      metadata["versions"][0]["representations"],
+      json!([{ "type": "sql", "sql": "SELECT id FROM t", "dialect": "spark" }])
+    );
+    assert_eq!(metadata["version-log"][0]["version-id"], 1);
+    assert_eq!(metadata["properties"]["comment"], "test");
+    assert!(metadata["location"].as_str().unwrap().ends_with("/ns/v"));
+    let metadata_location = body["metadata-location"].as_str().unwrap();
+    assert!(metadata_location.contains("/ns/v/metadata/00000-"));
+    let path = metadata_location.strip_prefix("file://").unwrap();
+    let stored: Value = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+    assert_eq!(&stored, metadata);
+
+    let response = client.head("/v1/namespaces/ns/views/v").dispatch().await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.head("/v1/namespaces/ns/views/t").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.get("/v1/namespaces/ns/views").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(
+      body["identifiers"],
+      json!([{ "namespace": ["ns"], "name": "v" }])
+    );
+
+    // metadata files are only written in the warehouse
+    let mut request = serde_json::to_value(mock_create_view_request("w")).unwrap();
+    request["location"] = json!(temp_dir.path().join("outside").to_str().unwrap());
+    let response = client
+      .post("/v1/namespaces/ns/views")
+      .header(ContentType::JSON)
+      .body(request.to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(!temp_dir.path().join("outside").exists());
+
+    // tables and views share their names
+    assert_eq!(create_view(&client, "ns", "v").await, Status::Conflict);
+    assert_eq!(create_view(&client, "ns", "t").await, Status::Conflict);
+    let response = client
+      .post("/v1/namespaces/ns/tables")
+      .header(ContentType::JSON)
+      .body(serde_json::to_vec(&mock_create_table_request("v")).unwrap())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::Conflict);
+    let response = client.get("/v1/namespaces/ns/tables").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(
+      body["identifiers"],
+      json!([{ "namespace": ["ns"], "name": "t" }])
+    );
+  }
+
+  #[rocket::async_test]
+  async fn test_replace_view() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    create_namespace_and_table(&client, "ns", "t").await;
+    assert_eq!(create_view(&client, "ns", "v").await, Status::Ok);
+    let url = "/v1/namespaces/ns/views/v";
+    let body = client.get(url).dispatch().await.into_json::<Value>().await;
+    let uuid = body.unwrap()["metadata"]["view-uuid"].clone();
+
+    let version = |dialects: &[&str]| {
+      let representations: Vec<_> = dialects
+        .iter()
+        .map(
+          |dialect| json!({ "type": "sql", "sql": "SELECT id, data FROM t", "dialect": dialect }),
+        )
+        .collect();
+      json!({
+        "action": "add-view-version",
+        "view-version": {
+          "version-id": 1,
+          "timestamp-ms": 2,
+          "schema-id": -1,
+          "representations": representations,
+          "default-namespace": ["ns"],
+        },
+      })
+    };
+    let replace = |dialects: &[&str]| {
+      json!({
+        "requirements": [{ "type": "assert-view-uuid", "uuid": uuid }],
+        "updates": [
+          { "action": "add-schema", "schema": { "type": "struct", "schema-id": 0, "fields": [
+            { "id": 1, "name": "id", "type": "long", "required": true },
+            { "id": 2, "name": "data", "type": "string", "required": false },
+          ] } },
+          version(dialects),
+          { "action": "set-current-view-version", "view-version-id": -1 },
+          { "action": "set-properties", "updates": { "owner": "etl" } },
+        ],
+      })
+    };
+
+    let (status, body) = commit_view(&client, url, replace(&["spark", "trino"])).await;
+    assert_eq!(status, Status::Ok);
+    let metadata = &body["metadata"];
+    assert_eq!(metadata["current-version-id"], 2);
+    assert_eq!(metadata["versions"].as_array().unwrap().len(), 2);
+    assert_eq!(metadata["versions"][1]["schema-id"], 1);
+    assert_eq!(
+      metadata["versions"][1]["representations"][1]["dialect"],
+      "trino"
+    );
+    assert_eq!(metadata["schemas"].as_array().unwrap().len(), 2);
+    assert_eq!(metadata["version-log"].as_array().unwrap().len(), 2);
+    assert_eq!(metadata["properties"]["owner"], "etl");
+    assert!(body["metadata-location"]
+      .as_str()
+      .unwrap()
+      .contains("/metadata/00001-"));
+
+    // the same version is not added twice, and the current version can go back
+    let (status, body) = commit_view(&client, url, replace(&["spark", "trino"])).await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body["metadata"]["versions"].as_array().unwrap().len(), 2);
+    let rollback = json!({
+      "updates": [{ "action": "set-current-view-version", "view-version-id": 1 }],
+    });
+    let (status, body) = commit_view(&client, url, rollback).await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body["metadata"]["current-version-id"], 1);
+    let log: Vec<_> = body["metadata"]["version-log"]
+      .as_array()
+      .unwrap()
+      .iter()
+      .map(|x| x["version-id"].clone())
+      .collect();
+    assert_eq!(log, vec![json!(1), json!(2), json!(1)]);
+
+    // one query per dialect
+    let (status, _) = commit_view(&client, url, replace(&["spark", "Spark"])).await;
+    assert_eq!(status, Status::BadRequest);
+    let mut stale = replace(&["hive"]);
+    stale["requirements"][0]["uuid"] = json!("other");
+    let (status, _) = commit_view(&client, url, stale).await;
+    assert_eq!(status, Status::Conflict);
+    let body = client.get(url).dispatch().await.into_json::<Value>().await;
+    assert_eq!(body.unwrap()["metadata"]["current-version-id"], 1);
+
+    let (status, _) = commit_view(
+      &client,
+      "/v1/namespaces/ns/views/missing",
+      replace(&["spark"]),
+    )
+    .await;
+    assert_eq!(status, Status::NotFound);
+  }
+
+  #[rocket::async_test]
+  async fn test_rename_and_drop_view() {
+    let temp_dir = tempfile::tempdir().expect("failed to create a temporary directory");
+    let client = create_mock_client(temp_dir.path().to_path_buf()).await;
+
+    create_namespace_and_table(&client, "a", "t").await;
+    create_namespace_and_table(&client, "b", "u").await;
+    assert_eq!(create_view(&client, "a", "v").await, Status::Ok);
+    let rename = |source: (&str, &str), destination: (&str, &str)| {
+      let request = json!({
+        "source": { "namespace": [source.0], "name": source.1 },
+        "destination": { "namespace": [destination.0], "name": destination.1 },
+      });
+      client
+        .post("/v1/views/rename")
+        .header(ContentType::JSON)
+        .body(request.to_string())
+        .dispatch()
+    };
+
+    assert_eq!(
+      rename(("a", "v"), ("b", "u")).await.status(),
+      Status::Conflict
+    );
+    assert_eq!(
+      rename(("a", "missing"), ("b", "w")).await.status(),
+      Status::NotFound
+    );
+    assert_eq!(
+      rename(("a", "v"), ("b", "w")).await.status(),
+      Status::NoContent
+    );
+    let response = client.get("/v1/namespaces/a/views/v").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.get("/v1/namespaces/a/views").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["identifiers"], json!([]));
+
+    // views move with their namespace
+    let response = client
+      .post("/v1/namespaces/rename")
+      .header(ContentType::JSON)
+      .body(json!({ "source": ["b"], "destination": ["c"] }).to_string())
+      .dispatch()
+      .await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.get("/v1/namespaces/c/views/w").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["metadata"]["versions"][0]["version-id"], 1);
+
+    let response = client.delete("/v1/namespaces/c/views/w").dispatch().await;
+    assert_eq!(response.status(), Status::NoContent);
+    let response = client.delete("/v1/namespaces/c/views/w").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+    let response = client.get("/v1/namespaces/c/views").dispatch().await;
+    let body = response.into_json::<Value>().await.unwrap();
+    assert_eq!(body["identifiers"], json!([]));
+  }
+}
@@ -0,0 +1,101 @@
+use crate::{
+  common::result::{ErrorType, Location, Result},
+  err,
+  server::routes::request::PageRequest,
+};
+
+// page size used when a client sends a page token without a page size
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+pub struct Page<T> {
+  pub items: Vec<T>,
+  // None on the last page
+  pub next_page_token: Option<String>,
+}
+
+// The token is the last name of the previous page, hex encoded so clients treat it as opaque.
+fn encode_token(name: &str) -> String {
+  name.bytes().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_token(token: &str) -> Result<String> {
+  let bytes: Option<Vec<u8>> = (0..token.len())
+    .step_by(2)
+    .map(|i| u8::from_str_radix(token.get(i..i + 2)?, 16).ok())
+    .collect();
+  match bytes.and_then(|bytes| String::from_utf8(bytes).ok()) {
+    Some(name) => Ok(name),
+    None => err!(
+      ErrorType::BadRequest,
+      Location::Request,
+      format!("Invalid page token {}", token)
+    ),
+  }
+}
+
+// Names are returned in sorted order. Without `pageToken` and `pageSize` they all are, otherwise
+// each page starts after the last name of the previous one, so names added or removed between
+// requests do not shift the pages.
+pub fn paginate(mut names: Vec<String>, request: &PageRequest) -> Result<Page<String>> {
+  names.sort();
+  if request.page_token.is_none() && request.page_size.is_none() {
+    return Ok(Page {
+      items: names,
+      next_page_token: None,
+    });
+  }
+  let page_size = request.page_size.unwrap_or(DEFAULT_PAGE_SIZE);
+  if page_size == 0 {
+    return err!(
+      ErrorType::BadRequest,
+      Location::Request,
+      "Page size must be positive".to_string()
+    );
+  }
+  // an empty token asks for the first page
+  if let Some(token) = request.page_token.as_deref().filter(|x| !x.is_empty()) {
+    let last = decode_token(token)?;
+    names.retain(|name| *name > last);
+  }
+  let next_page_token = match names.len() > page_size {
+    true => Some(encode_token(&names[page_size - 1])),
+    false => None,
+  };
+  names.truncate(page_size);
+  Ok(Page {
+    items: names,
+    next_page_token,
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn test_paginate() {
+    let names = |x: &[&str]| x.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    let request = |page_token: Option<&str>, page_size: Option<usize>| PageRequest {
+      page_token: page_token.map(|x| x.to_string()),
+      page_size,
+    };
+
+    let page = paginate(names(&["c", "a", "b"]), &request(None, None)).unwrap();
+    assert_eq!(page.items, names(&["a", "b", "c"]));
+    assert_eq!(page.next_page_token, None);
+    let page = paginate(vec![], &request(None, None)).unwrap();
+    assert!(page.items.is_empty());
+
+    let page = paginate(names(&["c", "a", "b"]), &request(Some(""), Some(2))).unwrap();
+    assert_eq!(page.items, names(&["a", "b"]));
+    let token = page.next_page_token.unwrap();
+    // "b" is dropped and "ab" added before the next page is requested
+    let page = paginate(names(&["c", "a", "ab"]), &request(Some(&token), Some(2))).unwrap();
+    assert_eq!(page.items, names(&["c"]));
+    assert_eq!(page.next_page_token, None);
+
+    assert!(paginate(names(&["a"]), &request(Some("zz"), None)).is_err());
+    assert!(paginate(names(&["a"]), &request(Some("6"), None)).is_err());
+    assert!(paginate(names(&["a"]), &request(None, Some(0))).is_err());
+  }
+}
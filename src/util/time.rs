@@ -7,3 +7,11 @@ pub fn now() -> u64 {
     .expect("Time went backwards")
     .as_secs()
 }
+
+pub fn now_millis() -> i64 {
+  let current_time = SystemTime::now();
+  current_time
+    .duration_since(UNIX_EPOCH)
+    .expect("Time went backwards")
+    .as_millis() as i64
+}